@@ -0,0 +1,236 @@
+use crate::error::Span;
+use crate::lexer::{AddOp, Compare, Logic, MulOp, Token, Type, UnaryOp};
+use std::collections::BTreeSet;
+use std::rc::Rc;
+
+/// An AST node together with the source location it was parsed from.
+#[derive(Debug, Clone)]
+pub(crate) struct Node<T> {
+    pub(crate) kind: T,
+    pub(crate) span: Span,
+}
+
+impl<T> Node<T> {
+    pub(crate) fn new(kind: T) -> Node<T> {
+        Node {
+            kind,
+            span: Span::default(),
+        }
+    }
+
+    pub(crate) fn with_span(mut self, span: Span) -> Node<T> {
+        self.span = span;
+        self
+    }
+}
+
+// Spans are only positional metadata: two trees are equal when their shape is.
+impl<T: PartialEq> PartialEq for Node<T> {
+    fn eq(&self, other: &Node<T>) -> bool {
+        self.kind == other.kind
+    }
+}
+
+/// The name anonymous functions are declared with, which no script can refer to.
+pub(crate) const LAMBDA: &str = "<lambda>";
+
+pub(crate) type ExprNode = Node<Expr>;
+pub(crate) type StmtNode = Node<Stmt>;
+pub(crate) type Block = Vec<StmtNode>;
+
+/// Operators taking two operands.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum BinOp {
+    Add(AddOp),
+    Mul(MulOp),
+    Compare(Compare),
+    Logic(Logic),
+}
+
+impl BinOp {
+    /// The operator a binary token stands for; compound assignments map to
+    /// the arithmetic they apply.
+    pub(crate) fn from_token(token: &Token) -> Option<BinOp> {
+        match token {
+            Token::ADDOP(op) | Token::ADDASSIGN(op) => Some(BinOp::Add(*op)),
+            Token::MULOP(op) | Token::MULASSIGN(op) => Some(BinOp::Mul(*op)),
+            Token::COMPARE(op) => Some(BinOp::Compare(*op)),
+            Token::LOGIC(op) => Some(BinOp::Logic(*op)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Expr {
+    Int(i32),
+    Float(f64),
+    Bool(bool),
+    Str(String),
+    Char(char),
+    Var(String),
+    /// A call of whatever function value the callee evaluates to.
+    Call(Box<ExprNode>, Vec<ExprNode>),
+    /// An anonymous function, which captures the variables it uses from the
+    /// scopes around it.
+    Lambda(Rc<FnDecl>),
+    Unary(UnaryOp, Box<ExprNode>),
+    Binary(BinOp, Box<ExprNode>, Box<ExprNode>),
+    /// `target = value`, or `target op= value` when the operator is present.
+    Assign(Option<BinOp>, Box<ExprNode>, Box<ExprNode>),
+    Cast(Type, Box<ExprNode>),
+    /// `value[index]`.
+    Index(Box<ExprNode>, Box<ExprNode>),
+    /// The null pointer.
+    Null,
+    /// `value.field`.
+    Field(Box<ExprNode>, String),
+    /// A struct of the named type, its fields initialized in order from the
+    /// list; the fields past its end are zero.
+    Struct(String, Vec<ExprNode>),
+    /// An array of `usize` elements of the type, initialized from the list;
+    /// the elements past its end are zero.
+    Array(Type, usize, Vec<ExprNode>),
+}
+
+/// A function declared by a script. Hosts call it by name through the interpreter.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FnDecl {
+    pub(crate) name: String,
+    pub(crate) ret: Type,
+    pub(crate) params: Vec<(Type, String)>,
+    pub(crate) body: Block,
+}
+
+impl FnDecl {
+    /// Every variable name the body refers to, including in nested lambdas.
+    pub(crate) fn names(&self) -> BTreeSet<String> {
+        let mut names = BTreeSet::new();
+        block_names(&self.body, &mut names);
+        names
+    }
+}
+
+fn block_names(block: &[StmtNode], names: &mut BTreeSet<String>) {
+    for statement in block {
+        stmt_names(statement, names);
+    }
+}
+
+fn stmt_names(statement: &StmtNode, names: &mut BTreeSet<String>) {
+    match &statement.kind {
+        Stmt::Expr(expr) | Stmt::Return(Some(expr)) | Stmt::Decl(_, _, Some(expr)) => {
+            expr_names(expr, names)
+        }
+        Stmt::If(condition, body, otherwise) => {
+            expr_names(condition, names);
+            block_names(body, names);
+            if let Some(otherwise) = otherwise {
+                block_names(otherwise, names);
+            }
+        }
+        Stmt::While(condition, body) => {
+            expr_names(condition, names);
+            block_names(body, names);
+        }
+        Stmt::For(init, condition, step, body) => {
+            if let Some(init) = init {
+                stmt_names(init, names);
+            }
+            for expr in condition.iter().chain(step) {
+                expr_names(expr, names);
+            }
+            block_names(body, names);
+        }
+        Stmt::Block(block) => block_names(block, names),
+        // A named function only sees its own frame and the globals.
+        Stmt::FnDecl(_)
+        | Stmt::StructDecl(_)
+        | Stmt::Decl(_, _, None)
+        | Stmt::Return(None)
+        | Stmt::Break
+        | Stmt::Continue => {}
+    }
+}
+
+fn expr_names(expr: &ExprNode, names: &mut BTreeSet<String>) {
+    match &expr.kind {
+        Expr::Var(name) => {
+            names.insert(name.clone());
+        }
+        Expr::Call(callee, args) => {
+            expr_names(callee, names);
+            for arg in args {
+                expr_names(arg, names);
+            }
+        }
+        Expr::Lambda(decl) => block_names(&decl.body, names),
+        Expr::Unary(_, operand) | Expr::Cast(_, operand) | Expr::Field(operand, _) => {
+            expr_names(operand, names)
+        }
+        Expr::Binary(_, left, right) | Expr::Assign(_, left, right) | Expr::Index(left, right) => {
+            expr_names(left, names);
+            expr_names(right, names);
+        }
+        Expr::Struct(_, items) | Expr::Array(_, _, items) => {
+            for item in items {
+                expr_names(item, names);
+            }
+        }
+        Expr::Int(_)
+        | Expr::Float(_)
+        | Expr::Bool(_)
+        | Expr::Str(_)
+        | Expr::Char(_)
+        | Expr::Null => {}
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Stmt {
+    Expr(ExprNode),
+    Decl(Type, String, Option<ExprNode>),
+    FnDecl(Rc<FnDecl>),
+    StructDecl(Rc<StructDecl>),
+    /// `return`, with a value unless the function is `void`.
+    Return(Option<ExprNode>),
+    /// An `else if` is an else block holding a single if.
+    If(ExprNode, Block, Option<Block>),
+    While(ExprNode, Block),
+    For(
+        Option<Box<StmtNode>>,
+        Option<ExprNode>,
+        Option<ExprNode>,
+        Block,
+    ),
+    Block(Block),
+    Break,
+    Continue,
+}
+
+/// The layout of a struct declared by a script.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StructDecl {
+    pub(crate) name: String,
+    pub(crate) fields: Vec<(Type, String)>,
+}
+
+/// A parsed program, ready to be run by an interpreter any number of times.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Program {
+    pub(crate) body: Block,
+}
+
+impl Program {
+    /// The names of the globals running the program declares.
+    pub(crate) fn globals(&self) -> Vec<&str> {
+        self.body
+            .iter()
+            .filter_map(|statement| match &statement.kind {
+                Stmt::Decl(_, name, _) => Some(name.as_str()),
+                Stmt::FnDecl(decl) => Some(decl.name.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+}
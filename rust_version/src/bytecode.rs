@@ -0,0 +1,622 @@
+//! Compiles the syntax tree into bytecode for the VM in `vm.rs`. Locals are
+//! resolved to numbered slots of their call frame as they are compiled;
+//! globals are looked up by name when they are used, as the tree-walker
+//! does, since a function may use a global declared after it.
+use crate::ast::*;
+use crate::error::{Error, ErrorCode, Span};
+use crate::interpreter::Value;
+use crate::lexer::{Compare, Logic, Type, UnaryOp};
+use crate::memory::Region;
+use std::rc::Rc;
+
+/**
+ * One instruction. Operands are popped from the value stack; assignments,
+ * increments and `&` work on places, which have a stack of their own.
+ * Each instruction also has a span in its chunk, which errors point at.
+ */
+#[derive(Debug)]
+pub(crate) enum Op {
+    Const(Value),
+    Pop,
+    /// Pushes the value of a local.
+    Load(usize),
+    /// Pushes the value of a global.
+    LoadGlobal(Rc<str>),
+    /// Pushes the function a local holds, to be called.
+    LoadFn(usize, Rc<str>),
+    /// Pushes the function a global holds, to be called.
+    LoadFnGlobal(Rc<str>),
+    /// Checks that the value on top can be called.
+    Callable,
+    /// Calls the function below the arguments, which are at these spans.
+    Call(Box<[Span]>),
+    /// Makes a closure of a lambda, capturing locals by name and slot.
+    Closure(Rc<Chunk>, Box<[(String, usize)]>),
+    /// `!`, `-` or `+`, with the operand's span.
+    Unary(UnaryOp, Span),
+    /// Arithmetic, with the spans of both operands.
+    Arithmetic(BinOp, Span, Span),
+    Compare(Compare),
+    /// Replaces the value on top with whether it is true.
+    Truthy,
+    /// Jumps if the bool on top is this one and leaves it, else pops it.
+    Short(bool, usize),
+    Jump(usize),
+    /// Pops a condition and jumps if it is false.
+    JumpUnless(usize),
+    /// A cast, with the operand's span.
+    Cast(Type, Span),
+    /// Checks the value on top can be indexed: a string only when set.
+    Indexable(bool),
+    /// Pops an index and what it indexes, which is at this span.
+    Index(Span),
+    Field(String),
+    /// Converts the value on top into an element of an array of this type.
+    ArrayItem(Type),
+    /// Pops the items of an array of this type and length into a new block.
+    Array(Type, usize, usize, Region),
+    /// Checks a struct is declared and has room for this many initializers.
+    StructCheck(String, usize),
+    /// Converts the value on top into field `i` of a struct.
+    StructField(String, usize),
+    /// Pops the first fields of a struct and fills in the rest.
+    Struct(String, usize),
+    /// Pushes the place of a local.
+    Local(usize, Rc<str>),
+    /// Pushes the place of a global.
+    Global(Rc<str>),
+    /// Pops an index, at this span, and what it indexes; pushes the element's place.
+    Element(Span),
+    /// Pops a pointer and pushes the place it points to.
+    Deref,
+    /// Replaces the place on top with its field, named at this span.
+    Member(String, Span),
+    /// Pushes the value at the place on top, which stays.
+    Peek,
+    /// Pops a place and pushes its value.
+    Read,
+    /// Pops a place, at this span, and stores the value on top in it.
+    Store(Span),
+    /// Pops a place, at this span, and increments or decrements it.
+    Step(UnaryOp, Span),
+    /// Pops a place and pushes a pointer to it.
+    Address,
+    CheckType(Type),
+    /// Checks that a global isn't declared yet.
+    CheckGlobal(Rc<str>),
+    /// Declares a local in a slot, initialized from the stack when set.
+    Declare(usize, Type, Rc<str>, bool),
+    /// Declares a global, initialized from the stack when set.
+    DeclareGlobal(Rc<str>, Type, bool),
+    DeclareStruct(Rc<StructDecl>),
+    /// Opens a block scope.
+    Enter,
+    /// Closes a block scope, releasing what was allocated in it.
+    Leave,
+    /// Returns the value on top from the frame.
+    Return,
+    Fail(Error),
+}
+
+/// The bytecode of a function or of top level code.
+#[derive(Debug)]
+pub(crate) struct Chunk {
+    /// The function, or `None` for top level code.
+    pub(crate) decl: Option<Rc<FnDecl>>,
+    pub(crate) code: Vec<Op>,
+    pub(crate) spans: Vec<Span>,
+    /// How many locals a frame needs: the parameters come first, then the
+    /// function's own name, then captured variables.
+    pub(crate) slots: usize,
+    /// The captured variables and their slots.
+    pub(crate) captures: Vec<(String, usize)>,
+}
+
+impl Chunk {
+    /// Calls `callee` from the host: the callee and the arguments, at
+    /// `spans`, are on the stack when it starts.
+    pub(crate) fn call(spans: Box<[Span]>, span: Span) -> Chunk {
+        Chunk {
+            decl: None,
+            code: vec![Op::Call(spans), Op::Return],
+            spans: vec![span, span],
+            slots: 0,
+            captures: Vec::new(),
+        }
+    }
+}
+
+// A loop being compiled: jumps out of it are patched when it ends.
+struct Loop {
+    // Scopes open outside the body.
+    depth: usize,
+    breaks: Vec<usize>,
+    continues: Vec<usize>,
+}
+
+/// Compiles one function, or top level code, into a chunk.
+pub(crate) struct Compiler {
+    decl: Option<Rc<FnDecl>>,
+    code: Vec<Op>,
+    spans: Vec<Span>,
+    // Block scopes, innermost last, and the slot of each name declared in them.
+    scopes: Vec<Vec<(String, usize)>>,
+    slots: usize,
+    // Scopes open at runtime at this point, which break and continue close.
+    depth: usize,
+    loops: Vec<Loop>,
+}
+
+impl Compiler {
+    fn new(decl: Option<Rc<FnDecl>>) -> Compiler {
+        Compiler {
+            decl,
+            code: Vec::new(),
+            spans: Vec::new(),
+            scopes: Vec::new(),
+            slots: 0,
+            depth: 0,
+            loops: Vec::new(),
+        }
+    }
+
+    /// A program, whose top level declarations make globals.
+    pub(crate) fn program(body: &[StmtNode]) -> Chunk {
+        let mut compiler = Compiler::new(None);
+        for statement in body {
+            compiler.statement(statement);
+        }
+        compiler.finish(Vec::new())
+    }
+
+    /// A single statement, as the REPL runs it: an expression gives its value.
+    pub(crate) fn statement_chunk(statement: &StmtNode) -> Chunk {
+        let mut compiler = Compiler::new(None);
+        match &statement.kind {
+            Stmt::Expr(expr) => {
+                compiler.expr(expr);
+                compiler.emit(Op::Return, statement.span);
+            }
+            _ => compiler.statement(statement),
+        }
+        compiler.finish(Vec::new())
+    }
+
+    /**
+     * A function. `captured` names the variables a closure of it captures,
+     * which are bound in its frame unless a parameter shadows them.
+     */
+    pub(crate) fn function(decl: &Rc<FnDecl>, captured: &[String]) -> Chunk {
+        let mut compiler = Compiler::new(Some(Rc::clone(decl)));
+        // The frame's own scope, which the call opens.
+        compiler.scopes.push(Vec::new());
+        compiler.depth = 1;
+        for (_, param) in &decl.params {
+            compiler.declare(param);
+        }
+        compiler.declare(&decl.name);
+        let mut captures = Vec::new();
+        for name in captured {
+            if !compiler.declared_here(name) {
+                captures.push((name.clone(), compiler.declare(name)));
+            }
+        }
+        compiler.block(&decl.body);
+        compiler.finish(captures)
+    }
+
+    // Ends the chunk: falling off the end returns nothing.
+    fn finish(mut self, captures: Vec<(String, usize)>) -> Chunk {
+        let span = self.spans.last().copied().unwrap_or_default();
+        self.emit(Op::Const(Value::Void), span);
+        self.emit(Op::Return, span);
+        Chunk {
+            decl: self.decl,
+            code: self.code,
+            spans: self.spans,
+            slots: self.slots,
+            captures,
+        }
+    }
+
+    fn emit(&mut self, op: Op, span: Span) -> usize {
+        self.code.push(op);
+        self.spans.push(span);
+        self.code.len() - 1
+    }
+
+    // Points the jump at `at` to the next instruction.
+    fn patch(&mut self, at: usize) {
+        let target = self.code.len();
+        match &mut self.code[at] {
+            Op::Jump(to) | Op::JumpUnless(to) | Op::Short(_, to) => *to = target,
+            op => unreachable!("{:?} is not a jump", op),
+        }
+    }
+
+    /// Declarations outside any block or function make globals.
+    fn top_level(&self) -> bool {
+        self.decl.is_none() && self.scopes.is_empty()
+    }
+
+    fn declared_here(&self, name: &str) -> bool {
+        self.scopes
+            .last()
+            .is_some_and(|scope| scope.iter().any(|(declared, _)| declared == name))
+    }
+
+    // A new slot for `name` in the innermost scope.
+    fn declare(&mut self, name: &str) -> usize {
+        let slot = self.slots;
+        self.slots += 1;
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .push((name.to_string(), slot));
+        slot
+    }
+
+    /// The slot of the local `name` visible here, if it isn't a global.
+    fn resolve(&self, name: &str) -> Option<usize> {
+        self.scopes.iter().rev().find_map(|scope| {
+            scope
+                .iter()
+                .rev()
+                .find(|(declared, _)| declared == name)
+                .map(|(_, slot)| *slot)
+        })
+    }
+
+    fn block(&mut self, block: &[StmtNode]) {
+        let span = block
+            .first()
+            .map(|statement| statement.span)
+            .unwrap_or_default();
+        self.enter(span);
+        for statement in block {
+            self.statement(statement);
+        }
+        self.leave(span);
+    }
+
+    fn enter(&mut self, span: Span) {
+        self.emit(Op::Enter, span);
+        self.scopes.push(Vec::new());
+        self.depth += 1;
+    }
+
+    fn leave(&mut self, span: Span) {
+        self.emit(Op::Leave, span);
+        self.scopes.pop();
+        self.depth -= 1;
+    }
+
+    // `break` or `continue`: closes the scopes inside the loop and jumps,
+    // to a target patched when the loop ends. Outside a loop, as in the
+    // tree-walker, either one ends the function.
+    fn jump_out(&mut self, is_break: bool, span: Span) {
+        let depth = match self.loops.last() {
+            Some(innermost) => innermost.depth,
+            None => {
+                self.emit(Op::Const(Value::Void), span);
+                self.emit(Op::Return, span);
+                return;
+            }
+        };
+        for _ in depth..self.depth {
+            self.emit(Op::Leave, span);
+        }
+        let jump = self.emit(Op::Jump(0), span);
+        let innermost = self.loops.last_mut().unwrap();
+        if is_break {
+            innermost.breaks.push(jump);
+        } else {
+            innermost.continues.push(jump);
+        }
+    }
+
+    // Ends the innermost loop: continues go to `next`, breaks to here.
+    fn end_loop(&mut self, next: usize) {
+        let innermost = self.loops.pop().unwrap();
+        for jump in innermost.continues {
+            if let Op::Jump(to) = &mut self.code[jump] {
+                *to = next;
+            }
+        }
+        for jump in innermost.breaks {
+            self.patch(jump);
+        }
+    }
+
+    fn statement(&mut self, input: &StmtNode) {
+        let span = input.span;
+        match &input.kind {
+            Stmt::Expr(expr) => {
+                self.expr(expr);
+                self.emit(Op::Pop, span);
+            }
+            Stmt::Decl(var_type, name, init) => {
+                if self.top_level() {
+                    self.emit(Op::CheckGlobal(name.as_str().into()), span);
+                } else if self.declared_here(name) {
+                    let error =
+                        Error::new(ErrorCode::Redeclaration, span, "Variable already declared!");
+                    self.emit(Op::Fail(error), span);
+                    return;
+                }
+                if let Type::ARRAY(_) | Type::STRUCT(_) = var_type {
+                    self.emit(Op::CheckType(var_type.clone()), span);
+                }
+                if let Some(init) = init {
+                    self.expr(init);
+                }
+                // Declared after its initializer, which sees what it shadows.
+                let op = if self.top_level() {
+                    Op::DeclareGlobal(name.as_str().into(), var_type.clone(), init.is_some())
+                } else {
+                    let slot = self.declare(name);
+                    Op::Declare(slot, var_type.clone(), name.as_str().into(), init.is_some())
+                };
+                self.emit(op, span);
+            }
+            Stmt::FnDecl(decl) => {
+                self.emit(Op::Const(Value::Func(Rc::clone(decl))), span);
+                let name = decl.name.as_str().into();
+                let op = if self.top_level() {
+                    Op::DeclareGlobal(name, Type::FUNC, true)
+                } else if self.declared_here(&decl.name) {
+                    Op::Fail(Error::new(
+                        ErrorCode::Redeclaration,
+                        span,
+                        "Interpreting Error: Unable to declare Var.",
+                    ))
+                } else {
+                    Op::Declare(self.declare(&decl.name), Type::FUNC, name, true)
+                };
+                self.emit(op, span);
+            }
+            Stmt::StructDecl(decl) => {
+                self.emit(Op::DeclareStruct(Rc::clone(decl)), span);
+            }
+            Stmt::Return(value) => {
+                match value {
+                    Some(value) => self.expr(value),
+                    None => {
+                        self.emit(Op::Const(Value::Void), span);
+                    }
+                }
+                self.emit(Op::Return, span);
+            }
+            Stmt::If(condition, body, otherwise) => {
+                self.expr(condition);
+                let skip = self.emit(Op::JumpUnless(0), condition.span);
+                self.block(body);
+                match otherwise {
+                    Some(otherwise) => {
+                        let end = self.emit(Op::Jump(0), span);
+                        self.patch(skip);
+                        self.block(otherwise);
+                        self.patch(end);
+                    }
+                    None => self.patch(skip),
+                }
+            }
+            Stmt::While(condition, body) => {
+                let start = self.code.len();
+                self.expr(condition);
+                let exit = self.emit(Op::JumpUnless(0), condition.span);
+                self.loops.push(Loop {
+                    depth: self.depth,
+                    breaks: Vec::new(),
+                    continues: Vec::new(),
+                });
+                self.block(body);
+                self.emit(Op::Jump(start), span);
+                self.patch(exit);
+                self.end_loop(start);
+            }
+            Stmt::For(init, condition, step, body) => {
+                // The init declaration lives in a scope wrapping the whole loop.
+                self.enter(span);
+                if let Some(init) = init {
+                    self.statement(init);
+                }
+                let start = self.code.len();
+                let exit = condition.as_ref().map(|condition| {
+                    self.expr(condition);
+                    self.emit(Op::JumpUnless(0), condition.span)
+                });
+                self.loops.push(Loop {
+                    depth: self.depth,
+                    breaks: Vec::new(),
+                    continues: Vec::new(),
+                });
+                self.block(body);
+                let next = self.code.len();
+                if let Some(step) = step {
+                    self.expr(step);
+                    self.emit(Op::Pop, step.span);
+                }
+                self.emit(Op::Jump(start), span);
+                if let Some(exit) = exit {
+                    self.patch(exit);
+                }
+                self.end_loop(next);
+                self.leave(span);
+            }
+            Stmt::Block(block) => self.block(block),
+            Stmt::Break => self.jump_out(true, span),
+            Stmt::Continue => self.jump_out(false, span),
+        }
+    }
+
+    fn expr(&mut self, input: &ExprNode) {
+        let span = input.span;
+        let op = match &input.kind {
+            Expr::Int(n) => Op::Const(Value::Int(*n)),
+            Expr::Float(n) => Op::Const(Value::Float(*n)),
+            Expr::Bool(b) => Op::Const(Value::Bool(*b)),
+            Expr::Str(s) => Op::Const(Value::Str(s.clone())),
+            Expr::Char(c) => Op::Const(Value::Char(*c)),
+            Expr::Null => Op::Const(Value::Ptr(Type::NONE, None)),
+            Expr::Var(name) => match self.resolve(name) {
+                Some(slot) => Op::Load(slot),
+                None => Op::LoadGlobal(name.as_str().into()),
+            },
+            Expr::Call(callee, args) => {
+                match &callee.kind {
+                    Expr::Var(name) => {
+                        let op = match self.resolve(name) {
+                            Some(slot) => Op::LoadFn(slot, name.as_str().into()),
+                            None => Op::LoadFnGlobal(name.as_str().into()),
+                        };
+                        self.emit(op, span);
+                    }
+                    _ => {
+                        self.expr(callee);
+                        self.emit(Op::Callable, span);
+                    }
+                }
+                // Arguments are evaluated in the caller's scope.
+                for arg in args {
+                    self.expr(arg);
+                }
+                Op::Call(args.iter().map(|arg| arg.span).collect())
+            }
+            Expr::Lambda(decl) => {
+                // Every local the body names is captured.
+                let captures: Vec<(String, usize)> = decl
+                    .names()
+                    .into_iter()
+                    .filter_map(|name| self.resolve(&name).map(|slot| (name, slot)))
+                    .collect();
+                let names: Vec<String> = captures.iter().map(|(name, _)| name.clone()).collect();
+                let chunk = Compiler::function(decl, &names);
+                Op::Closure(Rc::new(chunk), captures.into_boxed_slice())
+            }
+            Expr::Unary(op, operand) => match op {
+                UnaryOp::PREINC | UnaryOp::PREDEC | UnaryOp::POSTINC | UnaryOp::POSTDEC => {
+                    self.place(operand);
+                    Op::Step(*op, operand.span)
+                }
+                UnaryOp::ADDR => {
+                    self.place(operand);
+                    self.emit(Op::Address, operand.span);
+                    return;
+                }
+                UnaryOp::DEREF => {
+                    self.expr(operand);
+                    self.emit(Op::Deref, operand.span);
+                    Op::Read
+                }
+                _ => {
+                    self.expr(operand);
+                    Op::Unary(*op, operand.span)
+                }
+            },
+            Expr::Binary(BinOp::Logic(op), left, right) => {
+                // `&&` and `||` short-circuit.
+                self.expr(left);
+                self.emit(Op::Truthy, left.span);
+                let done = self.emit(Op::Short(*op == Logic::OR, 0), span);
+                self.expr(right);
+                self.emit(Op::Truthy, right.span);
+                self.patch(done);
+                return;
+            }
+            Expr::Binary(op, left, right) => {
+                self.expr(left);
+                self.expr(right);
+                match op {
+                    BinOp::Compare(op) => Op::Compare(*op),
+                    _ => Op::Arithmetic(*op, left.span, right.span),
+                }
+            }
+            Expr::Assign(op, target, value) => {
+                // `x op= y` is `x = x op y` with `x` looked up once.
+                self.place(target);
+                if let Some(op) = op {
+                    self.emit(Op::Peek, target.span);
+                    self.expr(value);
+                    self.emit(Op::Arithmetic(*op, target.span, value.span), span);
+                } else {
+                    self.expr(value);
+                }
+                Op::Store(target.span)
+            }
+            Expr::Cast(target, operand) => {
+                self.expr(operand);
+                Op::Cast(target.clone(), operand.span)
+            }
+            Expr::Index(value, index) => {
+                self.expr(value);
+                self.emit(Op::Indexable(true), value.span);
+                self.expr(index);
+                self.emit(Op::Index(value.span), index.span);
+                return;
+            }
+            Expr::Array(elem, len, items) => {
+                for item in items {
+                    self.expr(item);
+                    self.emit(Op::ArrayItem(elem.clone()), item.span);
+                }
+                let region = if self.top_level() {
+                    Region::Global
+                } else {
+                    Region::Stack
+                };
+                Op::Array(elem.clone(), *len, items.len(), region)
+            }
+            Expr::Field(value, field) => {
+                self.expr(value);
+                Op::Field(field.clone())
+            }
+            Expr::Struct(name, items) => {
+                self.emit(Op::StructCheck(name.clone(), items.len()), span);
+                for (i, item) in items.iter().enumerate() {
+                    self.expr(item);
+                    self.emit(Op::StructField(name.clone(), i), item.span);
+                }
+                Op::Struct(name.clone(), items.len())
+            }
+        };
+        self.emit(op, span);
+    }
+
+    /**
+     * Pushes the place an assignment, an increment or `&` refers to: a
+     * variable, an element, what a pointer points to or a field of one of
+     * those.
+     */
+    fn place(&mut self, target: &ExprNode) {
+        let op = match &target.kind {
+            Expr::Var(name) => match self.resolve(name) {
+                Some(slot) => Op::Local(slot, name.as_str().into()),
+                None => Op::Global(name.as_str().into()),
+            },
+            Expr::Index(value, index) => {
+                self.expr(value);
+                self.emit(Op::Indexable(false), value.span);
+                self.expr(index);
+                self.emit(Op::Element(index.span), value.span);
+                return;
+            }
+            Expr::Unary(UnaryOp::DEREF, pointer) => {
+                self.expr(pointer);
+                self.emit(Op::Deref, pointer.span);
+                return;
+            }
+            Expr::Field(value, field) => {
+                self.place(value);
+                self.emit(Op::Member(field.clone(), target.span), value.span);
+                return;
+            }
+            _ => Op::Fail(Error::new(
+                ErrorCode::InvalidAssignment,
+                target.span,
+                "Interpreting error: can't assign value to non-variable",
+            )),
+        };
+        self.emit(op, target.span);
+    }
+}
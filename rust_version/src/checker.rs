@@ -0,0 +1,967 @@
+//! Semantic analysis: checks names and types over the whole AST before
+//! anything runs, so every mistake in a program is reported at once rather
+//! than one at a time as execution reaches it.
+use crate::ast::*;
+use crate::error::{Error, ErrorCode, Span};
+use crate::lexer::*;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// The parameters and result of something callable.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Signature {
+    pub(crate) params: Vec<(Type, String)>,
+    pub(crate) ret: Type,
+}
+
+impl Signature {
+    pub(crate) fn of(decl: &FnDecl) -> Signature {
+        Signature {
+            params: decl.params.clone(),
+            ret: decl.ret.clone(),
+        }
+    }
+}
+
+/// What a name refers to.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Symbol {
+    Var(Type),
+    Func(Signature),
+}
+
+/**
+ * Can a value of type `value` be stored where `target` is declared? The
+ * same conversions as `promote`: int to float or char, an array to a
+ * pointer to its elements, and void pointers to and from any pointer.
+ */
+pub(crate) fn assignable(target: &Type, value: &Type) -> bool {
+    match (target, value) {
+        (Type::ANY, _) | (_, Type::ANY) => true,
+        (Type::FLOAT, Type::INT) | (Type::CHAR, Type::INT) => true,
+        (Type::POINTER(target), Type::ARRAY(elem)) => target == elem,
+        (Type::POINTER(target), Type::POINTER(value)) => {
+            target == value || **target == Type::NONE || **value == Type::NONE
+        }
+        (target, value) => target == value,
+    }
+}
+
+/// Can an argument of type `arg` be passed for a parameter of type `param`?
+/// Like `assignable`, except that an int isn't narrowed into a char.
+pub(crate) fn accepts(param: &Type, arg: &Type) -> bool {
+    !(*param == Type::CHAR && *arg == Type::INT) && assignable(param, arg)
+}
+
+/// Types a condition, `!`, `&&` or `||` can test.
+fn truthy(value: &Type) -> bool {
+    matches!(
+        value,
+        Type::INT | Type::FLOAT | Type::BOOL | Type::CHAR | Type::POINTER(_) | Type::ANY
+    )
+}
+
+/// Types arithmetic works on; a char takes part as its code.
+fn numeric(value: &Type) -> bool {
+    matches!(value, Type::INT | Type::FLOAT | Type::CHAR)
+}
+
+/**
+ * Does running `block` never get past its end? It returns on every path, or
+ * loops forever; anything after such a statement is unreachable, so one is
+ * enough.
+ */
+fn returns(block: &[StmtNode]) -> bool {
+    block.iter().any(|statement| match &statement.kind {
+        Stmt::Return(_) => true,
+        Stmt::If(_, body, Some(otherwise)) => returns(body) && returns(otherwise),
+        Stmt::Block(block) => returns(block),
+        Stmt::While(condition, body) => always(condition) && !breaks(body),
+        Stmt::For(_, None, _, body) => !breaks(body),
+        Stmt::For(_, Some(condition), _, body) => always(condition) && !breaks(body),
+        _ => false,
+    })
+}
+
+/// Is `condition` a literal that is always true, as in `while (1)`?
+fn always(condition: &ExprNode) -> bool {
+    matches!(condition.kind, Expr::Bool(true)) || matches!(condition.kind, Expr::Int(n) if n != 0)
+}
+
+/// Can `block` break out of the loop it is the body of?
+fn breaks(block: &[StmtNode]) -> bool {
+    block.iter().any(|statement| match &statement.kind {
+        Stmt::Break => true,
+        Stmt::If(_, body, otherwise) => breaks(body) || otherwise.as_deref().is_some_and(breaks),
+        Stmt::Block(block) => breaks(block),
+        // A break in a nested loop leaves only that loop.
+        _ => false,
+    })
+}
+
+/// An array used as a value is a pointer to its first element.
+fn decay(value: Type) -> Type {
+    match value {
+        Type::ARRAY(elem) => Type::POINTER(elem),
+        value => value,
+    }
+}
+
+pub(crate) struct Checker {
+    globals: HashMap<String, Symbol>,
+    // Block scopes of the function being checked, innermost last. Empty at
+    // the top level, where declarations make globals.
+    scopes: Vec<HashMap<String, Symbol>>,
+    structs: HashMap<String, Rc<StructDecl>>,
+    // The function whose body is being checked.
+    function: Option<Rc<FnDecl>>,
+    // Function bodies wait until every global they might use is known.
+    pending: Vec<(Rc<FnDecl>, Span)>,
+    errors: Vec<Error>,
+}
+
+impl Checker {
+    pub(crate) fn new(
+        globals: HashMap<String, Symbol>,
+        structs: HashMap<String, Rc<StructDecl>>,
+    ) -> Checker {
+        Checker {
+            globals,
+            scopes: Vec::new(),
+            structs,
+            function: None,
+            pending: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    /**
+     * Checks top level statements, then the bodies of the functions they
+     * declare, and returns every error found in source order.
+     */
+    pub(crate) fn check(mut self, body: &[StmtNode]) -> Result<(), Vec<Error>> {
+        for statement in body {
+            self.statement(statement);
+        }
+        let mut next = 0;
+        while next < self.pending.len() {
+            let (decl, span) = self.pending[next].clone();
+            self.function_body(&decl, span);
+            next += 1;
+        }
+        if self.errors.is_empty() {
+            return Ok(());
+        }
+        self.errors.sort_by_key(|error| error.span().start);
+        Err(self.errors)
+    }
+
+    pub(crate) fn error(&mut self, error: Error) {
+        self.errors.push(error);
+    }
+
+    pub(crate) fn mismatch(&mut self, span: Span, message: String) -> Type {
+        self.error(Error::new(ErrorCode::TypeMismatch, span, message));
+        Type::ANY
+    }
+
+    pub(crate) fn lookup(&self, name: &str) -> Option<&Symbol> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .or_else(|| self.globals.get(name))
+    }
+
+    pub(crate) fn declare(&mut self, name: &str, symbol: Symbol, span: Span) {
+        let scope = match self.scopes.last_mut() {
+            Some(scope) => scope,
+            None => &mut self.globals,
+        };
+        if scope.insert(name.to_string(), symbol).is_some() {
+            self.errors.push(Error::new(
+                ErrorCode::Redeclaration,
+                span,
+                "Variable already declared!",
+            ));
+        }
+    }
+
+    /// Checks that the struct types `var_type` mentions are declared, and that
+    /// it isn't `void`, which nothing can be stored as.
+    pub(crate) fn check_type(&mut self, var_type: &Type, span: Span) {
+        match var_type {
+            Type::ARRAY(elem) => self.check_type(elem, span),
+            Type::NONE => self.error(Error::new(
+                ErrorCode::TypeMismatch,
+                span,
+                "Only functions and pointers can be void",
+            )),
+            Type::STRUCT(name) if !self.structs.contains_key(name) => self.error(Error::new(
+                ErrorCode::UnknownType,
+                span,
+                format!("struct {} is not declared", name),
+            )),
+            _ => {}
+        }
+    }
+
+    pub(crate) fn block(&mut self, block: &[StmtNode]) {
+        self.scopes.push(HashMap::new());
+        for statement in block {
+            self.statement(statement);
+        }
+        self.scopes.pop();
+    }
+
+    /**
+     * A function body runs in a frame of its own, which sees its parameters,
+     * itself and the globals, but not the locals of whoever declared it.
+     */
+    pub(crate) fn function_body(&mut self, decl: &Rc<FnDecl>, span: Span) {
+        let scopes = std::mem::take(&mut self.scopes);
+        self.frame(decl, span);
+        self.scopes = scopes;
+    }
+
+    /// A lambda's body also sees the locals around it, which it captures.
+    pub(crate) fn lambda(&mut self, decl: &Rc<FnDecl>, span: Span) -> Type {
+        self.frame(decl, span);
+        Type::FUNC
+    }
+
+    /**
+     * Checks a function body in a scope on top of the current ones, holding
+     * its parameters and, unless it is a lambda, the function itself.
+     */
+    pub(crate) fn frame(&mut self, decl: &Rc<FnDecl>, span: Span) {
+        self.scopes.push(HashMap::new());
+        let function = self.function.replace(Rc::clone(decl));
+        for (param_type, param) in &decl.params {
+            self.check_type(param_type, span);
+            self.declare(param, Symbol::Var(param_type.clone()), span);
+        }
+        if decl.name != LAMBDA {
+            self.declare(&decl.name, Symbol::Func(Signature::of(decl)), span);
+        }
+        self.block(&decl.body);
+        if decl.ret != Type::NONE && !returns(&decl.body) {
+            self.error(Error::new(
+                ErrorCode::MissingReturn,
+                span,
+                format!("{} can end without returning {:?}", decl.name, decl.ret),
+            ));
+        }
+        self.scopes.pop();
+        self.function = function;
+    }
+
+    pub(crate) fn statement(&mut self, input: &StmtNode) {
+        match &input.kind {
+            Stmt::Expr(expr) => {
+                self.expr(expr);
+            }
+            Stmt::Decl(var_type, name, init) => {
+                self.check_type(var_type, input.span);
+                if let Some(init) = init {
+                    let value = self.expr(init);
+                    if !assignable(var_type, &value) {
+                        self.mismatch(
+                            init.span,
+                            format!("{} is {:?}, not {:?}", name, var_type, value),
+                        );
+                    }
+                }
+                self.declare(name, Symbol::Var(var_type.clone()), input.span);
+            }
+            Stmt::FnDecl(decl) => {
+                self.declare(&decl.name, Symbol::Func(Signature::of(decl)), input.span);
+                self.pending.push((Rc::clone(decl), input.span));
+            }
+            Stmt::StructDecl(decl) => self.struct_decl(decl, input.span),
+            Stmt::Return(expr) => {
+                let (value, span) = match expr {
+                    Some(expr) => (self.expr(expr), expr.span),
+                    None => (Type::NONE, input.span),
+                };
+                let function = match &self.function {
+                    Some(function) => Rc::clone(function),
+                    None => return,
+                };
+                if function.ret == Type::NONE {
+                    if value != Type::NONE && value != Type::ANY {
+                        let message =
+                            format!("{} is void and can't return {:?}", function.name, value);
+                        self.mismatch(span, message);
+                    }
+                } else if !assignable(&function.ret, &value) {
+                    let message = format!(
+                        "{} returns {:?}, not {:?}",
+                        function.name, function.ret, value
+                    );
+                    self.mismatch(span, message);
+                }
+            }
+            Stmt::If(condition, body, otherwise) => {
+                self.condition(condition);
+                self.block(body);
+                if let Some(otherwise) = otherwise {
+                    self.block(otherwise);
+                }
+            }
+            Stmt::While(condition, body) => {
+                self.condition(condition);
+                self.block(body);
+            }
+            Stmt::For(init, condition, step, body) => {
+                self.scopes.push(HashMap::new());
+                if let Some(init) = init {
+                    self.statement(init);
+                }
+                if let Some(condition) = condition {
+                    self.condition(condition);
+                }
+                if let Some(step) = step {
+                    self.expr(step);
+                }
+                self.block(body);
+                self.scopes.pop();
+            }
+            Stmt::Block(block) => self.block(block),
+            Stmt::Break | Stmt::Continue => {}
+        }
+    }
+
+    pub(crate) fn struct_decl(&mut self, decl: &Rc<StructDecl>, span: Span) {
+        match self.structs.get(&decl.name) {
+            Some(existing) if existing == decl => {}
+            Some(_) => self.error(Error::new(
+                ErrorCode::Redeclaration,
+                span,
+                format!("struct {} is already declared", decl.name),
+            )),
+            None => {
+                for (field_type, _) in &decl.fields {
+                    self.check_type(field_type, span);
+                }
+                self.structs.insert(decl.name.clone(), Rc::clone(decl));
+            }
+        }
+    }
+
+    pub(crate) fn condition(&mut self, condition: &ExprNode) {
+        let value = self.expr(condition);
+        if !truthy(&value) {
+            self.mismatch(
+                condition.span,
+                format!("Condition must be a number or a bool, found {:?}", value),
+            );
+        }
+    }
+
+    /// The type of `input`, or `ANY` where it can't be known, as after an error.
+    pub(crate) fn expr(&mut self, input: &ExprNode) -> Type {
+        let span = input.span;
+        match &input.kind {
+            Expr::Int(_) => Type::INT,
+            Expr::Float(_) => Type::FLOAT,
+            Expr::Bool(_) => Type::BOOL,
+            Expr::Str(_) => Type::STRING,
+            Expr::Char(_) => Type::CHAR,
+            Expr::Null => Type::POINTER(Box::new(Type::NONE)),
+            Expr::Var(name) => self.var(name, span),
+            Expr::Call(callee, args) => self.call(callee, args, span),
+            Expr::Lambda(decl) => self.lambda(decl, span),
+            Expr::Unary(op, operand) => self.unary(*op, operand, span),
+            Expr::Binary(BinOp::Logic(_), left, right) => {
+                self.condition(left);
+                self.condition(right);
+                Type::BOOL
+            }
+            Expr::Binary(BinOp::Compare(op), left, right) => {
+                let (lhs, rhs) = (self.expr(left), self.expr(right));
+                self.compare(*op, lhs, rhs, span)
+            }
+            Expr::Binary(op, left, right) => {
+                let lhs = (self.expr(left), left.span);
+                let rhs = (self.expr(right), right.span);
+                self.arithmetic(*op, lhs, rhs, span)
+            }
+            Expr::Assign(op, target, value) => self.assign(*op, target, value, span),
+            Expr::Cast(target, operand) => self.cast(target, operand, span),
+            Expr::Index(value, index) => {
+                let indexed = self.expr(value);
+                self.index(indexed, value.span, index)
+            }
+            Expr::Field(value, field) => {
+                let base = self.expr(value);
+                self.field(base, field, span)
+            }
+            Expr::Array(elem, _, items) => {
+                self.check_type(elem, span);
+                for item in items {
+                    let value = self.expr(item);
+                    if !assignable(elem, &value) {
+                        self.mismatch(
+                            item.span,
+                            format!("Array element must be {:?}, is {:?}", elem, value),
+                        );
+                    }
+                }
+                Type::ARRAY(Box::new(elem.clone()))
+            }
+            Expr::Struct(name, items) => self.struct_value(name, items, span),
+        }
+    }
+
+    pub(crate) fn var(&mut self, name: &str, span: Span) -> Type {
+        match self.lookup(name) {
+            Some(Symbol::Var(var_type)) => var_type.clone(),
+            Some(Symbol::Func(_)) => Type::FUNC,
+            None => {
+                self.error(Error::new(
+                    ErrorCode::UndeclaredVariable,
+                    span,
+                    "Interpreting Error: Variable Not Declared",
+                ));
+                Type::ANY
+            }
+        }
+    }
+
+    /// Checks a call's arity and arguments; gives the callee's result type.
+    pub(crate) fn call(&mut self, callee: &ExprNode, args: &[ExprNode], span: Span) -> Type {
+        let name = match &callee.kind {
+            Expr::Var(name) => name,
+            // A function value: only known when it's called.
+            _ => {
+                let callee = self.expr(callee);
+                for arg in args {
+                    self.expr(arg);
+                }
+                return match callee {
+                    Type::FUNC | Type::ANY => Type::ANY,
+                    other => self.mismatch(span, format!("{:?} is not a function", other)),
+                };
+            }
+        };
+        let args: Vec<(Type, Span)> = args.iter().map(|arg| (self.expr(arg), arg.span)).collect();
+        let signature = match self.lookup(name) {
+            Some(Symbol::Func(signature)) => signature.clone(),
+            // A function passed in a variable: only known when it's called.
+            Some(Symbol::Var(Type::FUNC)) | Some(Symbol::Var(Type::ANY)) => return Type::ANY,
+            Some(Symbol::Var(_)) => {
+                return self.mismatch(span, format!("{} is not a function", name))
+            }
+            None => {
+                self.error(Error::new(
+                    ErrorCode::UndeclaredVariable,
+                    span,
+                    "Interpreting Error: Variable Not Declared",
+                ));
+                return Type::ANY;
+            }
+        };
+        if args.len() != signature.params.len() {
+            self.error(Error::new(
+                ErrorCode::ArityMismatch,
+                span,
+                format!(
+                    "{} takes {} arguments, {} given",
+                    name,
+                    signature.params.len(),
+                    args.len()
+                ),
+            ));
+        }
+        for ((param_type, param), (arg, arg_span)) in signature.params.iter().zip(args) {
+            if !accepts(param_type, &arg) {
+                self.mismatch(
+                    arg_span,
+                    format!(
+                        "{} is of incorrect type: Should be {:?}, is {:?}",
+                        param, param_type, arg
+                    ),
+                );
+            }
+        }
+        signature.ret
+    }
+
+    pub(crate) fn unary(&mut self, op: UnaryOp, operand: &ExprNode, span: Span) -> Type {
+        match op {
+            UnaryOp::PREINC | UnaryOp::PREDEC | UnaryOp::POSTINC | UnaryOp::POSTDEC => {
+                let target = self.place(operand);
+                match target {
+                    Type::ANY | Type::INT | Type::FLOAT | Type::CHAR | Type::POINTER(_) => target,
+                    other => self.mismatch(span, format!("Can't increment {:?}", other)),
+                }
+            }
+            UnaryOp::ADDR => match &operand.kind {
+                Expr::Field(..) => {
+                    self.error(Error::new(
+                        ErrorCode::Unsupported,
+                        operand.span,
+                        "Can't take the address of a struct field",
+                    ));
+                    Type::ANY
+                }
+                _ => Type::POINTER(Box::new(self.place(operand))),
+            },
+            UnaryOp::DEREF => {
+                let pointer = self.expr(operand);
+                self.deref(pointer, operand.span)
+            }
+            UnaryOp::NOT => {
+                self.condition(operand);
+                Type::BOOL
+            }
+            UnaryOp::PLUS | UnaryOp::MINUS => match self.expr(operand) {
+                Type::INT | Type::CHAR => Type::INT,
+                Type::FLOAT => Type::FLOAT,
+                Type::ANY => Type::ANY,
+                _ => self.mismatch(
+                    span,
+                    "Operand of unary + or - must be an int or a float".to_string(),
+                ),
+            },
+        }
+    }
+
+    /// What `*value` has the type of.
+    pub(crate) fn deref(&mut self, value: Type, span: Span) -> Type {
+        match value {
+            Type::POINTER(target) if *target == Type::NONE => {
+                self.mismatch(span, "Can't dereference a void pointer".to_string())
+            }
+            Type::POINTER(target) | Type::ARRAY(target) => *target,
+            Type::ANY => Type::ANY,
+            other => self.mismatch(span, format!("Can't dereference {:?}", other)),
+        }
+    }
+
+    /// The type stored at an assignment target, which must be a place.
+    pub(crate) fn place(&mut self, target: &ExprNode) -> Type {
+        match &target.kind {
+            Expr::Var(name) => self.var(name, target.span),
+            Expr::Index(value, index) => match self.expr(value) {
+                Type::STRING => {
+                    self.expr(index);
+                    self.mismatch(
+                        value.span,
+                        "Cannot assign to a char of a string".to_string(),
+                    )
+                }
+                indexed => self.index(indexed, value.span, index),
+            },
+            Expr::Unary(UnaryOp::DEREF, pointer) => {
+                let value = self.expr(pointer);
+                self.deref(value, pointer.span)
+            }
+            Expr::Field(value, field) => {
+                let base = self.place(value);
+                self.field(base, field, target.span)
+            }
+            _ => {
+                self.error(Error::new(
+                    ErrorCode::InvalidAssignment,
+                    target.span,
+                    "Interpreting error: can't assign value to non-variable",
+                ));
+                Type::ANY
+            }
+        }
+    }
+
+    pub(crate) fn assign(
+        &mut self,
+        op: Option<BinOp>,
+        target: &ExprNode,
+        value: &ExprNode,
+        span: Span,
+    ) -> Type {
+        let target_type = self.place(target);
+        let mut value_type = self.expr(value);
+        if let Type::ARRAY(_) = target_type {
+            self.error(Error::new(
+                ErrorCode::InvalidAssignment,
+                target.span,
+                "Arrays can't be assigned, only their elements",
+            ));
+            return Type::ANY;
+        }
+        if let Some(op) = op {
+            let current = (target_type.clone(), target.span);
+            value_type = self.arithmetic(op, current, (value_type, value.span), span);
+        }
+        if value_type == Type::NONE {
+            return self.mismatch(span, "Unable to resolve r-value".to_string());
+        }
+        if !assignable(&target_type, &value_type) {
+            return self.mismatch(
+                value.span,
+                format!("Can't assign {:?} to {:?}", value_type, target_type),
+            );
+        }
+        target_type
+    }
+
+    /// The type of arithmetic on two operands, following `Interpreter::arithmetic`.
+    pub(crate) fn arithmetic(
+        &mut self,
+        op: BinOp,
+        (lhs, lhs_span): (Type, Span),
+        (rhs, rhs_span): (Type, Span),
+        span: Span,
+    ) -> Type {
+        let pointer = |value: &Type| matches!(value, Type::POINTER(_) | Type::ARRAY(_));
+        if pointer(&lhs) || pointer(&rhs) {
+            return match (op, decay(lhs), decay(rhs)) {
+                (_, Type::ANY, _) | (_, _, Type::ANY) => Type::ANY,
+                (BinOp::Add(AddOp::PLUS), ptr @ Type::POINTER(_), Type::INT)
+                | (BinOp::Add(AddOp::PLUS), Type::INT, ptr @ Type::POINTER(_))
+                | (BinOp::Add(AddOp::MINUS), ptr @ Type::POINTER(_), Type::INT) => ptr,
+                (BinOp::Add(AddOp::MINUS), Type::POINTER(_), Type::POINTER(_)) => Type::INT,
+                (op, lhs, rhs) => self.mismatch(
+                    span,
+                    format!("Cannot apply {:?} to {:?} and {:?}", op, lhs, rhs),
+                ),
+            };
+        }
+        match (&lhs, &rhs, op) {
+            (Type::STRING, Type::STRING, BinOp::Add(AddOp::PLUS))
+            | (Type::STRING, Type::CHAR, BinOp::Add(AddOp::PLUS)) => return Type::STRING,
+            (Type::ANY, _, _) | (_, Type::ANY, _) => return Type::ANY,
+            _ => {}
+        }
+        match (numeric(&lhs), numeric(&rhs)) {
+            (true, true) if lhs == Type::FLOAT || rhs == Type::FLOAT => Type::FLOAT,
+            (true, true) => Type::INT,
+            (true, false) => {
+                self.mismatch(rhs_span, "R-value must be float or digit item".to_string())
+            }
+            (false, _) => {
+                self.mismatch(lhs_span, "L-value must be float or digit item".to_string())
+            }
+        }
+    }
+
+    pub(crate) fn compare(&mut self, op: Compare, lhs: Type, rhs: Type, span: Span) -> Type {
+        match (&lhs, &rhs) {
+            (Type::ANY, _) | (_, Type::ANY) => {}
+            (Type::STRING, Type::STRING) | (Type::POINTER(_), Type::POINTER(_)) => {}
+            (lhs, rhs) if numeric(lhs) && numeric(rhs) => {}
+            (Type::BOOL, Type::BOOL) => {
+                if let Compare::LT | Compare::GT | Compare::LE | Compare::GE = op {
+                    self.mismatch(
+                        span,
+                        "bools can only be compared with == and !=".to_string(),
+                    );
+                }
+            }
+            _ => {
+                self.mismatch(span, format!("Cannot compare {:?} with {:?}", lhs, rhs));
+            }
+        }
+        Type::BOOL
+    }
+
+    pub(crate) fn cast(&mut self, target: &Type, operand: &ExprNode, span: Span) -> Type {
+        let value = self.expr(operand);
+        let valid = match target {
+            _ if value == Type::ANY => true,
+            Type::BOOL => truthy(&value),
+            Type::CHAR => matches!(value, Type::CHAR | Type::INT),
+            Type::INT | Type::FLOAT => {
+                matches!(value, Type::CHAR | Type::INT | Type::FLOAT | Type::BOOL)
+            }
+            _ => false,
+        };
+        if !valid {
+            self.mismatch(span, format!("Cannot cast {:?} to {:?}", value, target));
+        }
+        target.clone()
+    }
+
+    /// The type of `value[index]`, given the type of `value`.
+    pub(crate) fn index(&mut self, value: Type, value_span: Span, index: &ExprNode) -> Type {
+        match self.expr(index) {
+            Type::INT | Type::ANY => {}
+            other => {
+                self.mismatch(
+                    index.span,
+                    format!("Index must be an int, found {:?}", other),
+                );
+            }
+        }
+        match value {
+            Type::STRING => Type::CHAR,
+            Type::ARRAY(_) | Type::POINTER(_) => self.deref(value, value_span),
+            Type::ANY => Type::ANY,
+            other => self.mismatch(value_span, format!("Cannot index {:?}", other)),
+        }
+    }
+
+    /// The type of `value.field`, given the type of `value`.
+    pub(crate) fn field(&mut self, value: Type, field: &str, span: Span) -> Type {
+        let decl = match value {
+            Type::STRUCT(ref name) => match self.structs.get(name) {
+                Some(decl) => Rc::clone(decl),
+                // Already reported where the struct type was used.
+                None => return Type::ANY,
+            },
+            Type::ANY => return Type::ANY,
+            other => return self.mismatch(span, format!("{:?} has no fields", other)),
+        };
+        match decl.fields.iter().find(|(_, name)| name == field) {
+            Some((field_type, _)) => field_type.clone(),
+            None => {
+                self.error(Error::new(
+                    ErrorCode::UnknownField,
+                    span,
+                    format!("struct {} has no field {}", decl.name, field),
+                ));
+                Type::ANY
+            }
+        }
+    }
+
+    pub(crate) fn struct_value(&mut self, name: &str, items: &[ExprNode], span: Span) -> Type {
+        let struct_type = Type::STRUCT(name.to_string());
+        let decl = match self.structs.get(name) {
+            Some(decl) => Rc::clone(decl),
+            None => {
+                self.check_type(&struct_type, span);
+                return Type::ANY;
+            }
+        };
+        if items.len() > decl.fields.len() {
+            self.mismatch(
+                span,
+                format!(
+                    "struct {} has {} fields, {} given",
+                    name,
+                    decl.fields.len(),
+                    items.len()
+                ),
+            );
+        }
+        for ((field_type, field), item) in decl.fields.iter().zip(items) {
+            let value = self.expr(item);
+            if !assignable(field_type, &value) {
+                self.mismatch(
+                    item.span,
+                    format!("{}.{} is {:?}, not {:?}", name, field, field_type, value),
+                );
+            }
+        }
+        struct_type
+    }
+}
+
+#[cfg(test)]
+mod checker_tests {
+    use super::*;
+    use crate::interpreter::{Interpreter, Value};
+
+    fn check(source: &str) -> Result<(), Vec<Error>> {
+        Interpreter::new().check(&Program::parse(source).unwrap())
+    }
+
+    fn codes(source: &str) -> Vec<ErrorCode> {
+        check(source).unwrap_err().iter().map(Error::code).collect()
+    }
+
+    #[test]
+    fn checker_accepts_valid_program() {
+        assert_eq!(
+            Ok(()),
+            check(
+                "{
+                    struct P { int x; float y; };
+                    fn f(float m)->float { m * 2 }
+                    int a[3] = {1, 2, 3};
+                    int *p = a;
+                    struct P q = {1, 2};
+                    float r = f(*p + q.y);
+                    char c = 'a';
+                    c += 1;
+                    print(r);
+                }"
+            )
+        );
+    }
+
+    #[test]
+    fn checker_reports_every_error() {
+        let source = "{
+            missing = 1;
+            int a = \"text\";
+            sqrt();
+            int a;
+            nothing();
+            3 = a;
+            struct P { int x; };
+            struct P { int y; };
+            struct P p;
+            &p.x;
+        }";
+        let errors = check(source).unwrap_err();
+        assert_eq!(
+            vec![
+                ErrorCode::UndeclaredVariable,
+                ErrorCode::TypeMismatch,
+                ErrorCode::ArityMismatch,
+                ErrorCode::Redeclaration,
+                ErrorCode::UndeclaredVariable,
+                ErrorCode::InvalidAssignment,
+                ErrorCode::Redeclaration,
+                ErrorCode::Unsupported,
+            ],
+            errors.iter().map(Error::code).collect::<Vec<_>>()
+        );
+        // Nothing ran, so none of them is a runtime error.
+        for error in errors.iter() {
+            assert!(matches!(error, Error::Type { .. }), "{:?}", error);
+        }
+    }
+
+    #[test]
+    fn checker_assignments() {
+        assert_eq!(
+            vec![ErrorCode::TypeMismatch],
+            codes("{ int a = 1; a = true; }")
+        );
+        assert_eq!(Ok(()), check("{ float a = 1.0; a = 2; }"));
+        assert_eq!(
+            vec![ErrorCode::InvalidAssignment],
+            codes("{ int a[2]; int b[2]; a = b; }")
+        );
+    }
+
+    #[test]
+    fn checker_operands() {
+        assert_eq!(vec![ErrorCode::TypeMismatch], codes("{ 1 + true }"));
+        assert_eq!(vec![ErrorCode::TypeMismatch], codes("{ \"a\" < 1 }"));
+        assert_eq!(vec![ErrorCode::TypeMismatch], codes("{ if \"a\" { 1; } }"));
+        assert_eq!(Ok(()), check("{ string s = \"a\" + 'b'; s[0] == 'a' }"));
+    }
+
+    #[test]
+    fn checker_returns() {
+        assert_eq!(
+            vec![ErrorCode::TypeMismatch],
+            codes("{ fn f()->int { return \"no\"; } }")
+        );
+        assert_eq!(Ok(()), check("{ fn f()->float { return 1; } }"));
+        assert_eq!(
+            vec![ErrorCode::TypeMismatch],
+            codes("{ fn f()->int { return; } }")
+        );
+    }
+
+    #[test]
+    fn checker_void_functions() {
+        assert_eq!(
+            Ok(()),
+            check("{ fn f(int *p) { if (*p) { return; } *p = 1; } fn g() -> void { print(1) } }")
+        );
+        assert_eq!(
+            vec![ErrorCode::TypeMismatch],
+            codes("{ fn f() { return 1; } }")
+        );
+        assert_eq!(
+            vec![ErrorCode::TypeMismatch],
+            codes("{ fn f() { } int a = f(); }")
+        );
+        assert_eq!(vec![ErrorCode::TypeMismatch], codes("{ void a; }"));
+    }
+
+    #[test]
+    fn checker_void_tail_expression() {
+        assert_eq!(Ok(()), check("{ fn f() { int x = 1; x = 3 } }"));
+        assert_eq!(Ok(()), check("{ fn f(int a) { if (a) { a + 1 } } }"));
+        assert_eq!(
+            Value::Int(3),
+            Interpreter::new()
+                .eval("{ int x = 1; fn f() { x = 3 } f(); x }")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn checker_missing_return() {
+        assert_eq!(
+            vec![ErrorCode::MissingReturn],
+            codes("{ fn f(int a)->int { if (a) { return 1; } } }")
+        );
+        assert_eq!(
+            vec![ErrorCode::MissingReturn],
+            codes("{ fn f()->int { while (1) { break; } } }")
+        );
+        assert_eq!(
+            Ok(()),
+            check("{ fn f(int a)->int { if (a) { return 1; } else { return 2; } } }")
+        );
+        assert_eq!(
+            Ok(()),
+            check("{ fn f()->int { for (;;) { while (1) { break; } return 1; } } }")
+        );
+    }
+
+    #[test]
+    fn checker_function_bodies_see_only_their_frame() {
+        assert_eq!(
+            vec![ErrorCode::UndeclaredVariable],
+            codes("{ fn f()->int { int local = 1; local } local }")
+        );
+        // Globals declared after the function are visible once it runs.
+        assert_eq!(Ok(()), check("{ fn f()->int { later } int later = 2; }"));
+    }
+
+    #[test]
+    fn checker_runs_before_execution() {
+        let mut interpreter = Interpreter::new();
+        let err = interpreter
+            .eval("{ int a = 1; set(); a = \"text\"; }")
+            .unwrap_err();
+        assert_eq!(ErrorCode::UndeclaredVariable, err.code());
+        // Nothing ran, so `a` was never declared.
+        assert_eq!(None, interpreter.get_global("a"));
+    }
+
+    #[test]
+    fn checker_lambdas() {
+        assert_eq!(
+            Ok(()),
+            check("{ fn make(int n)->fn { int k = 2; return fn (int x)->int { x * n + k }; } make(1)(2) }")
+        );
+        assert_eq!(
+            vec![ErrorCode::MissingReturn, ErrorCode::TypeMismatch],
+            codes("{ fn f = fn (int x)->int { if (x) { return true; } }; }")
+        );
+        assert_eq!(
+            vec![ErrorCode::UndeclaredVariable],
+            codes("{ fn f = fn () { int inner = 1; }; inner }")
+        );
+        assert_eq!(vec![ErrorCode::TypeMismatch], codes("{ int n = 1; n(2) }"));
+        assert_eq!(vec![ErrorCode::TypeMismatch], codes("{ (1 + 2)(3) }"));
+    }
+
+    #[test]
+    fn checker_sees_earlier_globals() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .eval("{ fn twice(int n)->int { n * 2 } }")
+            .unwrap();
+        assert_eq!(
+            vec![ErrorCode::TypeMismatch],
+            interpreter
+                .check(&Program::parse("{ twice(1.5) }").unwrap())
+                .unwrap_err()
+                .iter()
+                .map(Error::code)
+                .collect::<Vec<_>>()
+        );
+    }
+}
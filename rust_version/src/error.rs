@@ -0,0 +1,335 @@
+use std::fmt;
+
+/// Location of a token or node in the source text.
+///
+/// `start` and `end` are byte offsets into the source, `line` and `col` are
+/// 1-based and point at the first character of the span.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Span {
+    /// Byte offset of the first character.
+    pub start: usize,
+    /// Byte offset one past the last character.
+    pub end: usize,
+    /// 1-based line of the first character.
+    pub line: usize,
+    /// 1-based column of the first character, counted in chars.
+    pub col: usize,
+}
+
+/// Stable identifiers for every error the interpreter can raise.
+///
+/// The codes returned by `as_str` are part of the public contract: tools may
+/// match on them, so a code is never reused or renumbered. The number says
+/// which stage first raised a code, while `category` says which `Error`
+/// variant carries it; the two differ for the E0400 to E0404 name errors,
+/// which the checker now finds before a program runs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorCode {
+    // Lexing
+    /// The source is empty.
+    EmptyInput,
+    /// A character that starts no token.
+    UnrecognizedToken,
+    /// A number literal that doesn't fit its type.
+    InvalidNumber,
+    /// A string literal missing its closing quote.
+    UnterminatedString,
+    /// A backslash followed by a char that isn't a C escape.
+    InvalidEscape,
+    /// A char literal that is empty, unterminated or holds several chars.
+    InvalidChar,
+    // Parsing
+    /// The parser found a token the grammar doesn't allow here.
+    UnexpectedToken,
+    /// `break` or `continue` outside of a loop.
+    MisplacedControlFlow,
+    // Typing, including the name errors the checker finds
+    /// A value of the wrong type for the operation.
+    TypeMismatch,
+    /// A call with the wrong number of arguments.
+    ArityMismatch,
+    /// A field the struct doesn't have.
+    UnknownField,
+    /// A struct type that was never declared.
+    UnknownType,
+    /// A function with a return type that can end without returning.
+    MissingReturn,
+    /// A name that was never declared.
+    UndeclaredVariable,
+    /// A name declared twice in the same scope.
+    Redeclaration,
+    /// Assignment to something that isn't a variable.
+    InvalidAssignment,
+    /// A construct this stage doesn't support.
+    Unsupported,
+    // Runtime
+    /// A variable read before it was given a value.
+    UninitializedVariable,
+    /// Int division or modulo by zero.
+    DivisionByZero,
+    /// Int arithmetic overflowed under the trap policy.
+    IntegerOverflow,
+    /// A function registered by the host reported an error.
+    NativeFailure,
+    /// An index outside the value being indexed.
+    IndexOutOfBounds,
+    /// A cast of a value the target type can't represent.
+    InvalidCast,
+    /// A dereference of, or arithmetic on, `NULL`.
+    NullPointer,
+    /// A pointer into memory whose lifetime has ended.
+    DanglingPointer,
+    /// A use of heap memory after it was freed.
+    UseAfterFree,
+    /// A second free of the same heap memory.
+    DoubleFree,
+    /// A free of memory that malloc didn't return.
+    InvalidFree,
+    /// Calls nested deeper than the interpreter's call depth limit.
+    StackOverflow,
+    /// A bug in the interpreter itself.
+    Internal,
+}
+
+impl ErrorCode {
+    /// The stable code, such as `E0400`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::EmptyInput => "E0100",
+            ErrorCode::UnrecognizedToken => "E0101",
+            ErrorCode::InvalidNumber => "E0102",
+            ErrorCode::UnterminatedString => "E0103",
+            ErrorCode::InvalidEscape => "E0104",
+            ErrorCode::InvalidChar => "E0105",
+            ErrorCode::UnexpectedToken => "E0200",
+            ErrorCode::MisplacedControlFlow => "E0201",
+            ErrorCode::TypeMismatch => "E0300",
+            ErrorCode::ArityMismatch => "E0301",
+            ErrorCode::UnknownField => "E0302",
+            ErrorCode::UnknownType => "E0303",
+            ErrorCode::MissingReturn => "E0304",
+            ErrorCode::UndeclaredVariable => "E0400",
+            ErrorCode::UninitializedVariable => "E0401",
+            ErrorCode::Redeclaration => "E0402",
+            ErrorCode::InvalidAssignment => "E0403",
+            ErrorCode::Unsupported => "E0404",
+            ErrorCode::DivisionByZero => "E0405",
+            ErrorCode::IntegerOverflow => "E0406",
+            ErrorCode::NativeFailure => "E0407",
+            ErrorCode::IndexOutOfBounds => "E0408",
+            ErrorCode::InvalidCast => "E0409",
+            ErrorCode::NullPointer => "E0410",
+            ErrorCode::DanglingPointer => "E0411",
+            ErrorCode::UseAfterFree => "E0412",
+            ErrorCode::DoubleFree => "E0413",
+            ErrorCode::InvalidFree => "E0414",
+            ErrorCode::StackOverflow => "E0415",
+            ErrorCode::Internal => "E0499",
+        }
+    }
+
+    /// The category of every error with this code, whichever stage finds it.
+    pub fn category(self) -> Category {
+        match self {
+            ErrorCode::EmptyInput
+            | ErrorCode::UnrecognizedToken
+            | ErrorCode::InvalidNumber
+            | ErrorCode::UnterminatedString
+            | ErrorCode::InvalidEscape
+            | ErrorCode::InvalidChar => Category::Lex,
+            ErrorCode::UnexpectedToken | ErrorCode::MisplacedControlFlow => Category::Parse,
+            ErrorCode::TypeMismatch
+            | ErrorCode::ArityMismatch
+            | ErrorCode::UnknownField
+            | ErrorCode::UnknownType
+            | ErrorCode::MissingReturn
+            | ErrorCode::UndeclaredVariable
+            | ErrorCode::Redeclaration
+            | ErrorCode::InvalidAssignment
+            | ErrorCode::Unsupported => Category::Type,
+            ErrorCode::UninitializedVariable
+            | ErrorCode::DivisionByZero
+            | ErrorCode::IntegerOverflow
+            | ErrorCode::NativeFailure
+            | ErrorCode::IndexOutOfBounds
+            | ErrorCode::InvalidCast
+            | ErrorCode::NullPointer
+            | ErrorCode::DanglingPointer
+            | ErrorCode::UseAfterFree
+            | ErrorCode::DoubleFree
+            | ErrorCode::InvalidFree
+            | ErrorCode::StackOverflow
+            | ErrorCode::Internal => Category::Runtime,
+        }
+    }
+}
+
+/// Which `Error` variant carries a code.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Category {
+    /// `Error::Lex`.
+    Lex,
+    /// `Error::Parse`.
+    Parse,
+    /// `Error::Type`.
+    Type,
+    /// `Error::Runtime`.
+    Runtime,
+}
+
+/// Everything that can go wrong while lexing, parsing or running a program.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error {
+    /// The source could not be split into tokens.
+    Lex {
+        /// What went wrong.
+        code: ErrorCode,
+        /// Where it went wrong.
+        span: Span,
+        /// A human readable description.
+        message: String,
+    },
+    /// The tokens don't form a valid program.
+    Parse {
+        /// What went wrong.
+        code: ErrorCode,
+        /// Where it went wrong.
+        span: Span,
+        /// A human readable description.
+        message: String,
+    },
+    /// The program is ill-typed, or uses a name or construct wrongly.
+    Type {
+        /// What went wrong.
+        code: ErrorCode,
+        /// Where it went wrong.
+        span: Span,
+        /// A human readable description.
+        message: String,
+    },
+    /// The program failed while running.
+    Runtime {
+        /// What went wrong.
+        code: ErrorCode,
+        /// Where it went wrong.
+        span: Span,
+        /// A human readable description.
+        message: String,
+    },
+}
+
+impl Error {
+    /// An error of the variant `code.category()` names.
+    pub(crate) fn new(code: ErrorCode, span: Span, message: impl Into<String>) -> Error {
+        let message = message.into();
+        match code.category() {
+            Category::Lex => Error::Lex {
+                code,
+                span,
+                message,
+            },
+            Category::Parse => Error::Parse {
+                code,
+                span,
+                message,
+            },
+            Category::Type => Error::Type {
+                code,
+                span,
+                message,
+            },
+            Category::Runtime => Error::Runtime {
+                code,
+                span,
+                message,
+            },
+        }
+    }
+
+    pub(crate) fn with_span(mut self, new_span: Span) -> Error {
+        match &mut self {
+            Error::Lex { span, .. }
+            | Error::Parse { span, .. }
+            | Error::Type { span, .. }
+            | Error::Runtime { span, .. } => *span = new_span,
+        }
+        self
+    }
+
+    /// The stable code identifying the kind of error.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Error::Lex { code, .. }
+            | Error::Parse { code, .. }
+            | Error::Type { code, .. }
+            | Error::Runtime { code, .. } => *code,
+        }
+    }
+
+    /// Where in the source the error was raised.
+    pub fn span(&self) -> Span {
+        match self {
+            Error::Lex { span, .. }
+            | Error::Parse { span, .. }
+            | Error::Type { span, .. }
+            | Error::Runtime { span, .. } => *span,
+        }
+    }
+
+    /// The human readable description.
+    pub fn message(&self) -> &str {
+        match self {
+            Error::Lex { message, .. }
+            | Error::Parse { message, .. }
+            | Error::Type { message, .. }
+            | Error::Runtime { message, .. } => message,
+        }
+    }
+
+    /**
+     * Formats the error as `file:line:col: [code] message`, followed by the
+     * offending line of `source` and a caret underneath the span.
+     */
+    pub fn render(&self, source: &str, file: &str) -> String {
+        let span = self.span();
+        let line = source
+            .lines()
+            .nth(span.line.saturating_sub(1))
+            .unwrap_or("");
+        let mut marker: String = line
+            .chars()
+            .take(span.col.saturating_sub(1))
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let width = source
+            .get(span.start..span.end)
+            .map_or(0, |text| text.chars().take_while(|c| *c != '\n').count());
+        marker.push_str(&"^".repeat(width.max(1)));
+        format!(
+            "{}:{}:{}: [{}] {}\n{}\n{}",
+            file,
+            span.line,
+            span.col,
+            self.code().as_str(),
+            self.message(),
+            line,
+            marker
+        )
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let span = self.span();
+        write!(
+            f,
+            "{}:{}: [{}] {}",
+            span.line,
+            span.col,
+            self.code().as_str(),
+            self.message()
+        )
+    }
+}
+
+impl std::error::Error for Error {}
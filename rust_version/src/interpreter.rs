@@ -0,0 +1,4019 @@
+use crate::ast::*;
+use crate::bytecode::{Chunk, Compiler};
+use crate::checker::{Checker, Signature, Symbol};
+use crate::error::{Error, ErrorCode, Span};
+use crate::lexer::*;
+use crate::memory::{Allocation, Leak, Memory, Pointer, Region};
+use crate::parser::Parser;
+use crate::stdlib;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::rc::Rc;
+
+/// The most elements an array can have, so a huge one is an error in the
+/// script instead of taking down the host.
+pub(crate) const MAX_ARRAY_LEN: usize = 1 << 22;
+
+/// Runtime values.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    /// An `int`.
+    Int(i32),
+    /// A `float`.
+    Float(f64),
+    /// A `bool`.
+    Bool(bool),
+    /// A `string`.
+    Str(String),
+    /// A `char`.
+    Char(char),
+    /// An array and the type of its elements. Arrays are shared, not copied,
+    /// when passed around, so a function can fill in its caller's array.
+    Array(Type, Rc<Allocation>),
+    /// A pointer and the type it points to, or `None` for `NULL`.
+    Ptr(Type, Option<Pointer>),
+    /// A struct and its fields, in declaration order. Unlike arrays, structs
+    /// are copied when assigned or passed.
+    Struct(Rc<StructDecl>, Vec<Value>),
+    /// A function declared by a script.
+    Func(Rc<FnDecl>),
+    /// An anonymous function and the variables it captured.
+    Closure(Rc<Closure>),
+    /// A function registered by the host.
+    Native(Rc<NativeFn>),
+    /// What a statement, or a function that returns nothing, evaluates to.
+    Void,
+}
+
+impl Value {
+    /// The type a variable needs to hold this value.
+    pub fn get_type(&self) -> Type {
+        match self {
+            Value::Int(_) => Type::INT,
+            Value::Float(_) => Type::FLOAT,
+            Value::Bool(_) => Type::BOOL,
+            Value::Str(_) => Type::STRING,
+            Value::Char(_) => Type::CHAR,
+            Value::Array(elem, _) => Type::ARRAY(Box::new(elem.clone())),
+            Value::Struct(decl, _) => Type::STRUCT(decl.name.clone()),
+            Value::Ptr(target, _) => Type::POINTER(Box::new(target.clone())),
+            Value::Func(_) | Value::Closure(_) | Value::Native(_) => Type::FUNC,
+            Value::Void => Type::NONE,
+        }
+    }
+}
+
+/**
+ * An anonymous function together with the local variables of the scopes
+ * around it that its body refers to. Captured variables are shared with
+ * those scopes, not copied, and live as long as the closure does.
+ */
+pub struct Closure {
+    pub(crate) decl: Rc<FnDecl>,
+    pub(crate) captures: Vec<(String, Var)>,
+}
+
+// Only the names: a captured variable may hold the closure itself.
+impl fmt::Debug for Closure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let captures: Vec<&String> = self.captures.iter().map(|(name, _)| name).collect();
+        f.debug_struct("Closure")
+            .field("decl", &self.decl.name)
+            .field("captures", &captures)
+            .finish()
+    }
+}
+
+// Each evaluation of a lambda makes a new closure, equal only to itself.
+impl PartialEq for Closure {
+    fn eq(&self, other: &Closure) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+type NativeCallback = dyn Fn(&[Value]) -> Result<Value, String>;
+
+/// A prelude function that needs the interpreter itself, such as `malloc`.
+pub(crate) type Builtin = fn(&mut Interpreter, &[Value], Span) -> Result<Value, Error>;
+
+enum Callback {
+    Host(Box<NativeCallback>),
+    Builtin(Builtin),
+}
+
+/**
+ * A Rust function scripts can call. Its arguments are checked against `params`
+ * before the callback runs, and its result against `ret` after.
+ */
+pub struct NativeFn {
+    name: String,
+    params: Vec<Type>,
+    ret: Type,
+    func: Callback,
+}
+
+impl fmt::Debug for NativeFn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NativeFn")
+            .field("name", &self.name)
+            .field("params", &self.params)
+            .field("ret", &self.ret)
+            .finish()
+    }
+}
+
+// Closures can't be compared, so a native function is only equal to itself.
+impl PartialEq for NativeFn {
+    fn eq(&self, other: &NativeFn) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+/**
+ * Checks what a call to `decl` returned against its declared return type,
+ * promoting an int into a float result. The checker rules out most
+ * mismatches before a program runs; this catches values it can't see, such
+ * as a native's `ANY` result.
+ */
+pub(crate) fn check_return(decl: &FnDecl, result: Value, span: Span) -> Result<Value, Error> {
+    let result = promote(&decl.ret, result);
+    let result_type = result.get_type();
+    if decl.ret == Type::NONE && result_type != Type::NONE {
+        return Err(Error::new(
+            ErrorCode::TypeMismatch,
+            span,
+            format!("{} is void, but returned {:?}", decl.name, result_type),
+        ));
+    }
+    if decl.ret != Type::NONE && result_type == Type::NONE {
+        return Err(Error::new(
+            ErrorCode::MissingReturn,
+            span,
+            format!("{} ended without returning {:?}", decl.name, decl.ret),
+        ));
+    }
+    if result_type != decl.ret {
+        return Err(Error::new(
+            ErrorCode::TypeMismatch,
+            span,
+            format!(
+                "{} returns {:?}, not {:?}",
+                decl.name, decl.ret, result_type
+            ),
+        ));
+    }
+    Ok(result)
+}
+
+/**
+ * Checks an argument against the type of the parameter it's bound to. An int
+ * argument is promoted when the parameter is a float.
+ */
+pub(crate) fn check_arg(
+    param: &str,
+    param_type: &Type,
+    arg: Value,
+    span: Span,
+) -> Result<Value, Error> {
+    // Arrays decay and NULL converts to any pointer parameter.
+    let arg = match param_type {
+        Type::POINTER(_) => promote(param_type, arg),
+        _ => arg,
+    };
+    let arg_type = arg.get_type();
+    if arg_type != *param_type
+        && *param_type != Type::ANY
+        && !(arg_type == Type::INT && *param_type == Type::FLOAT)
+    {
+        return Err(Error::new(
+            ErrorCode::TypeMismatch,
+            span,
+            format!(
+                "{} is of incorrect type: Should be {:?}, is {:?}",
+                param, param_type, arg_type
+            ),
+        ));
+    }
+    Ok(promote(param_type, arg))
+}
+
+// How scripts print values.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Int(n) => write!(f, "{}", n),
+            // Debug keeps the fraction, so 2.0 doesn't print as an int.
+            Value::Float(n) => write!(f, "{:?}", n),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Str(s) => write!(f, "{}", s),
+            Value::Char(c) => write!(f, "{}", c),
+            Value::Array(_, block) => {
+                write!(f, "{{")?;
+                for (i, item) in block.values().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    match item {
+                        Some(item) => write!(f, "{}", item)?,
+                        None => write!(f, "?")?,
+                    }
+                }
+                write!(f, "}}")
+            }
+            Value::Ptr(_, None) => write!(f, "NULL"),
+            Value::Ptr(_, Some(pointer)) => {
+                write!(f, "&{}[{}]", pointer.block.id, pointer.offset)
+            }
+            Value::Struct(decl, fields) => {
+                write!(f, "{} {{ ", decl.name)?;
+                for (i, ((_, name), value)) in decl.fields.iter().zip(fields).enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", name, value)?;
+                }
+                write!(f, " }}")
+            }
+            Value::Func(decl) => write!(f, "fn {}", decl.name),
+            Value::Closure(closure) => write!(f, "fn {}", closure.decl.name),
+            Value::Native(native) => write!(f, "fn {}", native.name),
+            Value::Void => write!(f, "void"),
+        }
+    }
+}
+
+/// How a statement finished.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Flow {
+    Normal,
+    Return(Value),
+    Break,
+    Continue,
+}
+
+/**
+ * C's usual arithmetic conversion: an int meeting a float is widened to a float.
+ */
+pub(crate) fn as_float(value: &Value) -> Option<f64> {
+    match value {
+        Value::Int(n) => Some(f64::from(*n)),
+        Value::Char(c) => Some(f64::from(u32::from(*c))),
+        Value::Float(n) => Some(*n),
+        _ => None,
+    }
+}
+
+/**
+ * C's integer promotion: a char taking part in arithmetic or a comparison
+ * does so as its code.
+ */
+pub(crate) fn as_int(value: Value) -> Value {
+    match value {
+        Value::Char(c) => Value::Int(u32::from(c) as i32),
+        value => value,
+    }
+}
+
+/// "1 cell" or "n cells".
+fn cells(n: usize) -> String {
+    format!("{} cell{}", n, if n == 1 { "" } else { "s" })
+}
+
+/// Pointers, and arrays which decay into them, take part in pointer arithmetic.
+fn is_pointer(value: &Value) -> bool {
+    matches!(value, Value::Ptr(..) | Value::Array(..))
+}
+
+/**
+ * `p + n`, `n + p` and `p - n` move a pointer by `n` elements; `p - q` is
+ * how many elements apart two pointers into the same array are.
+ */
+fn pointer_arithmetic(op: BinOp, lhs: Value, rhs: Value, span: Span) -> Result<Value, Error> {
+    let decay = |value| match value {
+        Value::Array(elem, block) => Value::Ptr(elem, Some(Pointer::new(block, 0))),
+        value => value,
+    };
+    let null = || Error::new(ErrorCode::NullPointer, span, "Arithmetic on a null pointer");
+    match (op, decay(lhs), decay(rhs)) {
+        (BinOp::Add(AddOp::PLUS), Value::Ptr(target, pointer), Value::Int(n))
+        | (BinOp::Add(AddOp::PLUS), Value::Int(n), Value::Ptr(target, pointer)) => {
+            let pointer = pointer.ok_or_else(null)?;
+            Ok(Value::Ptr(target, Some(pointer.add(n as isize))))
+        }
+        (BinOp::Add(AddOp::MINUS), Value::Ptr(target, pointer), Value::Int(n)) => {
+            let pointer = pointer.ok_or_else(null)?;
+            Ok(Value::Ptr(target, Some(pointer.add(-(n as isize)))))
+        }
+        (BinOp::Add(AddOp::MINUS), Value::Ptr(_, m), Value::Ptr(_, n)) => {
+            let (m, n) = (m.ok_or_else(null)?, n.ok_or_else(null)?);
+            if !Rc::ptr_eq(&m.block, &n.block) {
+                return Err(Error::new(
+                    ErrorCode::TypeMismatch,
+                    span,
+                    "Only pointers into the same array can be subtracted",
+                ));
+            }
+            i32::try_from(m.offset - n.offset)
+                .map(Value::Int)
+                .map_err(|_| {
+                    Error::new(
+                        ErrorCode::IntegerOverflow,
+                        span,
+                        "Pointer difference overflow",
+                    )
+                })
+        }
+        (_, lhs, rhs) => Err(Error::new(
+            ErrorCode::TypeMismatch,
+            span,
+            format!(
+                "Cannot apply {:?} to {:?} and {:?}",
+                op,
+                lhs.get_type(),
+                rhs.get_type()
+            ),
+        )),
+    }
+}
+
+/// Where an assignment or increment stores its result.
+pub(crate) enum Place<'a> {
+    Var(&'a str),
+    /// A variable that was already looked up, and its name.
+    Slot(&'a str, Var),
+    /// An array element or what a pointer points to, and the type it holds.
+    Cell(Type, Pointer),
+    /// A field of the struct stored at a place, by position.
+    Field(Box<Place<'a>>, usize),
+}
+
+/// An element of an array initializer, converted to the element type.
+pub(crate) fn array_item(elem: &Type, value: Value, span: Span) -> Result<Value, Error> {
+    let value = promote(elem, value);
+    if value.get_type() != *elem {
+        return Err(Error::new(
+            ErrorCode::TypeMismatch,
+            span,
+            format!(
+                "Array element must be {:?}, is {:?}",
+                elem,
+                value.get_type()
+            ),
+        ));
+    }
+    Ok(value)
+}
+
+/// The initializer of field `i` of a struct, converted to the field's type.
+pub(crate) fn struct_field(
+    decl: &StructDecl,
+    i: usize,
+    value: Value,
+    span: Span,
+) -> Result<Value, Error> {
+    let (field_type, field) = &decl.fields[i];
+    let value = promote(field_type, value);
+    if value.get_type() != *field_type {
+        return Err(Error::new(
+            ErrorCode::TypeMismatch,
+            span,
+            format!(
+                "{}.{} is {:?}, not {:?}",
+                decl.name,
+                field,
+                field_type,
+                value.get_type()
+            ),
+        ));
+    }
+    Ok(value)
+}
+
+/// The function the variable `name` holds, for a call at `span`.
+pub(crate) fn function(name: &str, var: Option<Var>, span: Span) -> Result<Value, Error> {
+    match var.map(|(_, block)| block.read(0)) {
+        Some(Some(value)) if value.get_type() == Type::FUNC => Ok(value),
+        Some(_) => Err(Error::new(
+            ErrorCode::TypeMismatch,
+            span,
+            format!("{} is not a function", name),
+        )),
+        None => Err(Error::new(
+            ErrorCode::UndeclaredVariable,
+            span,
+            "Interpreting Error: Variable Not Declared",
+        )),
+    }
+}
+
+/// A callee that isn't named, which must evaluate to a function.
+pub(crate) fn callable(value: Value, span: Span) -> Result<Value, Error> {
+    match value {
+        value if value.get_type() == Type::FUNC => Ok(value),
+        other => Err(Error::new(
+            ErrorCode::TypeMismatch,
+            span,
+            format!("{:?} is not a function", other.get_type()),
+        )),
+    }
+}
+
+/// The value of a variable, which must have been initialized.
+pub(crate) fn read_var((_, block): &Var, span: Span) -> Result<Value, Error> {
+    block.read(0).ok_or_else(|| {
+        Error::new(
+            ErrorCode::UninitializedVariable,
+            span,
+            "Interpreting Error: Variable not initialized",
+        )
+    })
+}
+
+/// The char with code `n`, if there is one.
+pub(crate) fn char_from(n: i32) -> Option<char> {
+    u32::try_from(n).ok().and_then(char::from_u32)
+}
+
+/**
+ * Implicit conversion of a value stored into a variable of type `var_type`.
+ * An int is promoted into a float, and narrowed into a char when it is a
+ * valid char code, so `c += 1` keeps `c` a char. An array decays into a
+ * pointer to its first element, and `NULL` and void pointers convert to
+ * and from any other pointer type.
+ */
+pub(crate) fn promote(var_type: &Type, value: Value) -> Value {
+    match (var_type, value) {
+        (Type::FLOAT, Value::Int(n)) => Value::Float(f64::from(n)),
+        (Type::CHAR, Value::Int(n)) => char_from(n).map_or(Value::Int(n), Value::Char),
+        (Type::POINTER(target), Value::Array(elem, block)) if **target == elem => {
+            Value::Ptr(elem, Some(Pointer::new(block, 0)))
+        }
+        (Type::POINTER(target), Value::Ptr(Type::NONE, pointer)) => {
+            Value::Ptr((**target).clone(), pointer)
+        }
+        (Type::POINTER(target), Value::Ptr(_, pointer)) if **target == Type::NONE => {
+            Value::Ptr(Type::NONE, pointer)
+        }
+        (_, value) => value,
+    }
+}
+
+/**
+ * What happens when int arithmetic leaves the i32 range. Results are computed
+ * exactly and then fitted, so debug and release builds agree.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OverflowPolicy {
+    /// Raise an IntegerOverflow error.
+    Trap,
+    /// Two's complement wraparound.
+    Wrap,
+    /// Clamp to i32::MIN or i32::MAX.
+    Saturate,
+}
+
+impl OverflowPolicy {
+    pub(crate) fn fit(self, exact: i64, span: Span) -> Result<i32, Error> {
+        if let Ok(n) = i32::try_from(exact) {
+            return Ok(n);
+        }
+        match self {
+            OverflowPolicy::Trap => Err(Error::new(
+                ErrorCode::IntegerOverflow,
+                span,
+                format!("Integer overflow: {} does not fit in an int", exact),
+            )),
+            OverflowPolicy::Wrap => Ok(exact as i32),
+            OverflowPolicy::Saturate => {
+                Ok(exact.clamp(i64::from(i32::MIN), i64::from(i32::MAX)) as i32)
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for OverflowPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "trap" => Ok(OverflowPolicy::Trap),
+            "wrap" => Ok(OverflowPolicy::Wrap),
+            "saturate" => Ok(OverflowPolicy::Saturate),
+            _ => Err(format!(
+                "unknown overflow policy '{}', expected trap, wrap or saturate",
+                s
+            )),
+        }
+    }
+}
+
+/// Which engine runs programs. Both give the same results and errors.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Engine {
+    /// Walk the syntax tree.
+    Tree,
+    /// Compile to bytecode and run it on a stack machine.
+    Vm,
+}
+
+impl std::str::FromStr for Engine {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tree" => Ok(Engine::Tree),
+            "vm" => Ok(Engine::Vm),
+            _ => Err(format!("unknown engine '{}', expected tree or vm", s)),
+        }
+    }
+}
+
+/// A variable: its declared type and the block holding its value.
+pub(crate) type Var = (Type, Rc<Allocation>);
+/// Call frames, innermost last, each a stack of block scopes.
+type Scope = Vec<Vec<HashMap<String, Var>>>;
+
+/// How many calls may be nested before a script is stopped with a stack overflow.
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 10_000;
+
+// When less than `STACK_RED_ZONE` bytes of Rust stack are left at a call, the
+// call runs on a fresh `STACK_SEGMENT` allocated on the heap, so recursion in
+// scripts is limited by `max_call_depth` rather than by the native stack.
+const STACK_RED_ZONE: usize = 256 * 1024;
+const STACK_SEGMENT: usize = 4 * 1024 * 1024;
+
+/**
+ * Runs programs and keeps their globals alive between runs, so a host can
+ * evaluate a script, then read its variables or call its functions.
+ */
+pub struct Interpreter {
+    pub(crate) global_vars: HashMap<String, Var>,
+    scope: Scope,
+    pub(crate) memory: Memory,
+    pub(crate) structs: HashMap<String, Rc<StructDecl>>,
+    pub(crate) overflow: OverflowPolicy,
+    // Script function calls in progress.
+    pub(crate) depth: usize,
+    pub(crate) max_call_depth: usize,
+    engine: Engine,
+    // Bytecode of the functions the VM has run, by declaration.
+    pub(crate) chunks: HashMap<*const FnDecl, Rc<Chunk>>,
+    // Programs that ran and declared globals, which running them again replaces.
+    ran: Vec<Program>,
+}
+
+impl Default for Interpreter {
+    fn default() -> Interpreter {
+        Interpreter::new()
+    }
+}
+
+impl Interpreter {
+    /**
+     * An interpreter that traps on int overflow, with the prelude installed:
+     * `print`, `println`, `abs`, `min`, `max`, `pow`, `sqrt`, `floor`,
+     * `len`, `substr`, `round`, `ceil`, `trunc`, `to_string`, `parse_int`,
+     * `parse_float`, `malloc`, `calloc` and `free`.
+     */
+    pub fn new() -> Interpreter {
+        let mut interpreter = Interpreter::without_prelude();
+        stdlib::install(&mut interpreter);
+        interpreter
+    }
+
+    /// An interpreter with no globals at all, not even the prelude.
+    pub fn without_prelude() -> Interpreter {
+        Interpreter {
+            global_vars: HashMap::new(),
+            scope: Vec::new(),
+            memory: Memory::default(),
+            structs: HashMap::new(),
+            overflow: OverflowPolicy::Trap,
+            depth: 0,
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            engine: Engine::Tree,
+            chunks: HashMap::new(),
+            ran: Vec::new(),
+        }
+    }
+
+    /// Chooses what int overflow does for the rest of this interpreter's life.
+    pub fn set_overflow_policy(&mut self, policy: OverflowPolicy) {
+        self.overflow = policy;
+    }
+
+    /**
+     * Limits how deeply script function calls may nest. A call past the limit
+     * fails with a stack overflow error instead of running. The default is
+     * `DEFAULT_MAX_CALL_DEPTH`.
+     */
+    pub fn set_max_call_depth(&mut self, depth: usize) {
+        self.max_call_depth = depth;
+    }
+
+    /**
+     * Chooses what runs programs, statements and calls from the host: the
+     * tree-walker, which is the default, or the bytecode VM.
+     */
+    pub fn set_engine(&mut self, engine: Engine) {
+        self.engine = engine;
+    }
+
+    /// Declarations outside any block or function make globals.
+    pub(crate) fn at_top_level(&self) -> bool {
+        self.scope.last().is_none_or(Vec::is_empty)
+    }
+
+    /// A block for `cells`, on the stack unless it belongs to a global.
+    pub(crate) fn allocate(&mut self, cells: Vec<Option<Value>>) -> Rc<Allocation> {
+        let region = if self.at_top_level() {
+            Region::Global
+        } else {
+            Region::Stack
+        };
+        self.memory.allocate(cells, region)
+    }
+
+    /// Opens a block scope in the current call frame.
+    pub(crate) fn push_scope(&mut self) {
+        self.scope.last_mut().unwrap().push(HashMap::new());
+        self.memory.enter();
+    }
+
+    /// Closes the innermost block scope, releasing what was allocated in it.
+    pub(crate) fn pop_scope(&mut self) {
+        self.scope.last_mut().unwrap().pop();
+        self.memory.leave();
+    }
+
+    pub(crate) fn update_var(
+        &mut self,
+        name: &str,
+        value: Value,
+        span: Span,
+    ) -> Result<Value, Error> {
+        match self.find_slot(name) {
+            Some(var) => self.write_var(name, &var, value, span),
+            None => Err(Error::new(
+                ErrorCode::UndeclaredVariable,
+                span,
+                "Variable not found/declared",
+            )),
+        }
+    }
+
+    /// Stores `value` in the variable `name`, converted to its type.
+    pub(crate) fn write_var(
+        &self,
+        name: &str,
+        (var_type, block): &Var,
+        value: Value,
+        span: Span,
+    ) -> Result<Value, Error> {
+        if let Type::ARRAY(_) = var_type {
+            return Err(Error::new(
+                ErrorCode::InvalidAssignment,
+                span,
+                "Arrays can't be assigned, only their elements",
+            ));
+        }
+        let value = promote(var_type, value);
+        // The checker rules out most mismatches; values it can't see, such as
+        // a native's `ANY` result, are caught here.
+        if value.get_type() != *var_type && *var_type != Type::ANY {
+            return Err(Error::new(
+                ErrorCode::TypeMismatch,
+                span,
+                format!("{} is {:?}, not {:?}", name, var_type, value.get_type()),
+            ));
+        }
+        block.write(0, value.clone());
+        Ok(value)
+    }
+
+    /**
+     * Search for var in lexical scopes, then global scope.
+     */
+    pub(crate) fn find_slot(&self, input: &str) -> Option<Var> {
+        for i in self.scope.last().into_iter().flatten().rev() {
+            if let Some(j) = i.get(input) {
+                return Some(j.clone());
+            }
+        }
+        self.global_vars.get(input).cloned()
+    }
+    /// A variable of the current call frame, not a global.
+    pub(crate) fn find_local(&self, input: &str) -> Option<Var> {
+        self.scope
+            .last()
+            .into_iter()
+            .flatten()
+            .rev()
+            .find_map(|scope| scope.get(input).cloned())
+    }
+
+    pub(crate) fn var_declared(&mut self, input: &str) -> bool {
+        if let Some(i) = self.scope.last().and_then(|frame| frame.last()) {
+            i.contains_key(input)
+        } else {
+            self.global_vars.contains_key(input)
+        }
+    }
+
+    pub(crate) fn declare_var(
+        &mut self,
+        name: String,
+        var_type: Type,
+        value: Option<Value>,
+        span: Span,
+    ) -> Result<(), Error> {
+        if self.at_top_level() {
+            return self.declare_global(name, var_type, value, span);
+        }
+        let value = value.map(|value| promote(&var_type, value));
+        let block = self.allocate(vec![value]);
+        if let Some(i) = self.scope.last_mut().and_then(|frame| frame.last_mut()) {
+            match i.insert(name, (var_type, block)) {
+                None => Ok(()),
+                Some(_) => Err(Error::new(
+                    ErrorCode::Redeclaration,
+                    span,
+                    "Interpreting Error: Unable to declare Var.",
+                )),
+            }
+        } else {
+            Err(Error::new(
+                ErrorCode::Internal,
+                span,
+                "Unknown Interpreting error, unable to declare var",
+            ))
+        }
+    }
+
+    /// Declares a global, whatever scope is running.
+    pub(crate) fn declare_global(
+        &mut self,
+        name: String,
+        var_type: Type,
+        value: Option<Value>,
+        span: Span,
+    ) -> Result<(), Error> {
+        let value = value.map(|value| promote(&var_type, value));
+        let block = self.memory.allocate(vec![value], Region::Global);
+        match self.global_vars.insert(name, (var_type, block)) {
+            None => Ok(()),
+            Some(_) => Err(Error::new(
+                ErrorCode::Redeclaration,
+                span,
+                "Interpreting Error: Unable to declare Var.",
+            )),
+        }
+    }
+
+    /**
+     * Conditions of ifs and loops: a number is true when non-zero, a bool is itself.
+     * Anything else is a type error.
+     */
+    pub(crate) fn is_truthy(&self, value: &Value, span: Span) -> Result<bool, Error> {
+        match value {
+            Value::Int(n) => Ok(*n != 0),
+            Value::Float(n) => Ok(*n != 0.0),
+            Value::Bool(b) => Ok(*b),
+            Value::Char(c) => Ok(*c != '\0'),
+            Value::Ptr(_, pointer) => Ok(pointer.is_some()),
+            _ => Err(Error::new(
+                ErrorCode::TypeMismatch,
+                span,
+                format!("Condition must be a number or a bool, found {:?}", value),
+            )),
+        }
+    }
+
+    pub(crate) fn condition(&mut self, condition: &ExprNode) -> Result<bool, Error> {
+        let value = self.eval_expr(condition)?;
+        self.is_truthy(&value, condition.span)
+    }
+
+    /**
+     * Runs a block in its own scope. Stops at the first statement that
+     * doesn't finish normally: a return, break or continue.
+     */
+    pub(crate) fn exec_block(&mut self, block: &[StmtNode]) -> Result<Flow, Error> {
+        self.push_scope();
+        let mut result = Ok(Flow::Normal);
+        for statement in block {
+            match self.exec(statement) {
+                Ok(Flow::Normal) => {}
+                other => {
+                    result = other;
+                    break;
+                }
+            }
+        }
+        self.pop_scope();
+        result
+    }
+
+    pub(crate) fn exec(&mut self, input: &StmtNode) -> Result<Flow, Error> {
+        match &input.kind {
+            Stmt::Expr(expr) => {
+                self.eval_expr(expr)?;
+                Ok(Flow::Normal)
+            }
+            Stmt::Decl(var_type, name, init) => {
+                if self.var_declared(name) {
+                    return Err(Error::new(
+                        ErrorCode::Redeclaration,
+                        input.span,
+                        "Variable already declared!",
+                    ));
+                }
+                self.check_type(var_type, input.span)?;
+                let value = match init {
+                    Some(init) => Some(self.eval_expr(init)?),
+                    None => None,
+                };
+                let value = self.initial(var_type, name, value, input.span)?;
+                self.declare_var(name.clone(), var_type.clone(), value, input.span)?;
+                Ok(Flow::Normal)
+            }
+            Stmt::FnDecl(decl) => {
+                self.declare_var(
+                    decl.name.clone(),
+                    Type::FUNC,
+                    Some(Value::Func(Rc::clone(decl))),
+                    input.span,
+                )?;
+                Ok(Flow::Normal)
+            }
+            Stmt::StructDecl(decl) => {
+                self.declare_struct(decl, input.span)?;
+                Ok(Flow::Normal)
+            }
+            Stmt::Return(Some(expr)) => Ok(Flow::Return(self.eval_expr(expr)?)),
+            Stmt::Return(None) => Ok(Flow::Return(Value::Void)),
+            Stmt::If(condition, body, otherwise) => {
+                if self.condition(condition)? {
+                    self.exec_block(body)
+                } else if let Some(otherwise) = otherwise {
+                    self.exec_block(otherwise)
+                } else {
+                    Ok(Flow::Normal)
+                }
+            }
+            Stmt::While(condition, body) => {
+                while self.condition(condition)? {
+                    match self.exec_block(body)? {
+                        Flow::Break => break,
+                        Flow::Normal | Flow::Continue => {}
+                        result => return Ok(result),
+                    }
+                }
+                Ok(Flow::Normal)
+            }
+            Stmt::For(init, condition, step, body) => {
+                // The init declaration lives in a scope wrapping the whole loop.
+                self.push_scope();
+                let result = self.exec_for(init, condition, step, body);
+                self.pop_scope();
+                result
+            }
+            Stmt::Block(block) => self.exec_block(block),
+            Stmt::Break => Ok(Flow::Break),
+            Stmt::Continue => Ok(Flow::Continue),
+        }
+    }
+
+    /// What a declared variable starts as: its initializer, converted to its type.
+    pub(crate) fn initial(
+        &self,
+        var_type: &Type,
+        name: &str,
+        init: Option<Value>,
+        span: Span,
+    ) -> Result<Option<Value>, Error> {
+        let value = match (init, var_type) {
+            (Some(init), _) => Some(promote(var_type, init)),
+            // Structs start zeroed, so their fields can be set one by one.
+            (None, Type::STRUCT(_)) => self.zero(var_type),
+            (None, _) => None,
+        };
+        if let Some(value) = &value {
+            if value.get_type() != *var_type {
+                return Err(Error::new(
+                    ErrorCode::TypeMismatch,
+                    span,
+                    format!("{} is {:?}, not {:?}", name, var_type, value.get_type()),
+                ));
+            }
+        }
+        Ok(value)
+    }
+
+    pub(crate) fn exec_for(
+        &mut self,
+        init: &Option<Box<StmtNode>>,
+        condition: &Option<ExprNode>,
+        step: &Option<ExprNode>,
+        body: &[StmtNode],
+    ) -> Result<Flow, Error> {
+        if let Some(init) = init {
+            self.exec(init)?;
+        }
+        loop {
+            if let Some(condition) = condition {
+                if !self.condition(condition)? {
+                    return Ok(Flow::Normal);
+                }
+            }
+            match self.exec_block(body)? {
+                Flow::Break => return Ok(Flow::Normal),
+                Flow::Normal | Flow::Continue => {}
+                result => return Ok(result),
+            }
+            if let Some(step) = step {
+                self.eval_expr(step)?;
+            }
+        }
+    }
+
+    /**
+     * Resolves what an assignment, an increment or `&` refers to: a variable,
+     * an element, what a pointer points to or a field of one of those. Any
+     * other expression (a literal, an operation or a call) is an error.
+     */
+    pub(crate) fn place<'a>(&mut self, target: &'a ExprNode) -> Result<Place<'a>, Error> {
+        match &target.kind {
+            Expr::Var(name) => Ok(Place::Var(name)),
+            Expr::Index(value, index) => {
+                let indexed = self.eval_expr(value)?;
+                self.element(indexed, value.span, index)
+            }
+            Expr::Unary(UnaryOp::DEREF, pointer) => {
+                let value = self.eval_expr(pointer)?;
+                self.deref(value, pointer.span)
+            }
+            Expr::Field(value, field) => {
+                let base = self.place(value)?;
+                let current = self.load_struct(&base, value.span)?;
+                let i = self.field_position(&current, field, target.span)?;
+                Ok(Place::Field(Box::new(base), i))
+            }
+            _ => Err(Error::new(
+                ErrorCode::InvalidAssignment,
+                target.span,
+                "Interpreting error: can't assign value to non-variable",
+            )),
+        }
+    }
+
+    /**
+     * Checks that `pointer` points into live memory and inside its allocation,
+     * and gives the position of the cell.
+     */
+    pub(crate) fn cell(&self, pointer: &Pointer, span: Span) -> Result<usize, Error> {
+        if !pointer.block.is_live() {
+            return Err(match pointer.block.region {
+                Region::Heap => Error::new(ErrorCode::UseAfterFree, span, "Use of freed memory"),
+                _ => Error::new(
+                    ErrorCode::DanglingPointer,
+                    span,
+                    "Pointer to memory that is no longer live",
+                ),
+            });
+        }
+        let len = pointer.block.len();
+        match usize::try_from(pointer.offset) {
+            Ok(i) if i < len => Ok(i),
+            _ => Err(Error::new(
+                ErrorCode::IndexOutOfBounds,
+                span,
+                format!(
+                    "Pointer offset {} out of bounds for length {}",
+                    pointer.offset, len
+                ),
+            )),
+        }
+    }
+
+    pub(crate) fn load(&mut self, place: &Place, span: Span) -> Result<Value, Error> {
+        match place {
+            Place::Var(name) => self.load_var(name, span),
+            Place::Slot(_, var) => read_var(var, span),
+            Place::Cell(cell_type, pointer) => {
+                let i = self.cell(pointer, span)?;
+                let zero = || match pointer.block.zeroed {
+                    true => self.zero(cell_type),
+                    false => None,
+                };
+                pointer.block.read(i).or_else(zero).ok_or_else(|| {
+                    Error::new(
+                        ErrorCode::UninitializedVariable,
+                        span,
+                        "Interpreting Error: Memory not initialized",
+                    )
+                })
+            }
+            Place::Field(base, i) => match self.load(base, span)? {
+                Value::Struct(_, mut fields) => Ok(fields.swap_remove(*i)),
+                other => Err(Error::new(
+                    ErrorCode::Internal,
+                    span,
+                    format!("{:?} has no fields", other.get_type()),
+                )),
+            },
+        }
+    }
+
+    /**
+     * The struct a field is set in. One in memory that was never written, as
+     * malloc leaves it, starts out zeroed so its fields can be set one by one.
+     */
+    pub(crate) fn load_struct(&mut self, base: &Place, span: Span) -> Result<Value, Error> {
+        if let Place::Cell(cell_type @ Type::STRUCT(_), pointer) = base {
+            let i = self.cell(pointer, span)?;
+            if let (None, Some(zero)) = (pointer.block.read(i), self.zero(cell_type)) {
+                return Ok(zero);
+            }
+        }
+        self.load(base, span)
+    }
+
+    /// Writes `value` to `place`, converted to its type. Yields what was stored.
+    pub(crate) fn store(
+        &mut self,
+        place: &Place,
+        value: Value,
+        span: Span,
+    ) -> Result<Value, Error> {
+        match place {
+            Place::Var(name) => self.update_var(name, value, span),
+            Place::Slot(name, var) => self.write_var(name, var, value, span),
+            Place::Cell(Type::ARRAY(_), _) => Err(Error::new(
+                ErrorCode::InvalidAssignment,
+                span,
+                "Arrays can't be assigned, only their elements",
+            )),
+            Place::Cell(cell_type, pointer) => {
+                let i = self.cell(pointer, span)?;
+                let value = promote(cell_type, value);
+                if value.get_type() != *cell_type {
+                    return Err(Error::new(
+                        ErrorCode::TypeMismatch,
+                        span,
+                        format!("Element must be {:?}, is {:?}", cell_type, value.get_type()),
+                    ));
+                }
+                pointer.block.write(i, value.clone());
+                Ok(value)
+            }
+            // Structs are values: the field is set on a copy that is stored back.
+            Place::Field(base, i) => {
+                let mut current = self.load_struct(base, span)?;
+                let value = match &mut current {
+                    Value::Struct(decl, fields) => {
+                        let (field_type, field) = &decl.fields[*i];
+                        let value = promote(field_type, value);
+                        if value.get_type() != *field_type {
+                            return Err(Error::new(
+                                ErrorCode::TypeMismatch,
+                                span,
+                                format!(
+                                    "{}.{} is {:?}, not {:?}",
+                                    decl.name,
+                                    field,
+                                    field_type,
+                                    value.get_type()
+                                ),
+                            ));
+                        }
+                        fields[*i] = value.clone();
+                        value
+                    }
+                    other => {
+                        return Err(Error::new(
+                            ErrorCode::Internal,
+                            span,
+                            format!("{:?} has no fields", other.get_type()),
+                        ))
+                    }
+                };
+                self.store(base, current, span)?;
+                Ok(value)
+            }
+        }
+    }
+
+    /**
+     * What `*value` refers to. `NULL` and pointers to `void` can't be
+     * dereferenced; an array can, giving its first element.
+     */
+    pub(crate) fn deref<'a>(&mut self, value: Value, span: Span) -> Result<Place<'a>, Error> {
+        match value {
+            Value::Ptr(_, None) => Err(Error::new(
+                ErrorCode::NullPointer,
+                span,
+                "Null pointer dereference",
+            )),
+            Value::Ptr(Type::NONE, _) => Err(Error::new(
+                ErrorCode::TypeMismatch,
+                span,
+                "Can't dereference a void pointer",
+            )),
+            Value::Ptr(target, Some(pointer)) => Ok(Place::Cell(target, pointer)),
+            Value::Array(elem, block) => Ok(Place::Cell(elem, Pointer::new(block, 0))),
+            other => Err(Error::new(
+                ErrorCode::TypeMismatch,
+                span,
+                format!("Can't dereference {:?}", other.get_type()),
+            )),
+        }
+    }
+
+    /// `&operand`: the address of a variable, an element or a pointed-to cell.
+    pub(crate) fn address_of(&mut self, operand: &ExprNode) -> Result<Value, Error> {
+        let place = self.place(operand)?;
+        self.address(place, operand.span)
+    }
+
+    /// A pointer to `place`, which `span` names.
+    pub(crate) fn address(&self, place: Place, span: Span) -> Result<Value, Error> {
+        match place {
+            Place::Var(name) => match self.find_slot(name) {
+                Some((var_type, block)) => Ok(Value::Ptr(var_type, Some(Pointer::new(block, 0)))),
+                None => Err(Error::new(
+                    ErrorCode::UndeclaredVariable,
+                    span,
+                    "Interpreting Error: Variable Not Declared",
+                )),
+            },
+            Place::Slot(_, (var_type, block)) => {
+                Ok(Value::Ptr(var_type, Some(Pointer::new(block, 0))))
+            }
+            Place::Cell(cell_type, pointer) => Ok(Value::Ptr(cell_type, Some(pointer))),
+            Place::Field(..) => Err(Error::new(
+                ErrorCode::Unsupported,
+                span,
+                "Can't take the address of a struct field",
+            )),
+        }
+    }
+
+    pub(crate) fn load_var(&mut self, name: &str, span: Span) -> Result<Value, Error> {
+        match self.find_slot(name) {
+            Some(var) => read_var(&var, span),
+            None => Err(Error::new(
+                ErrorCode::UndeclaredVariable,
+                span,
+                "Interpreting Error: Variable Not Declared",
+            )),
+        }
+    }
+
+    /**
+     * `x = y`, or `x op= y` which is `x = x op y` with `x` looked up once.
+     * Yields the stored value.
+     */
+    pub(crate) fn assign(
+        &mut self,
+        op: Option<BinOp>,
+        target: &ExprNode,
+        value: &ExprNode,
+        span: Span,
+    ) -> Result<Value, Error> {
+        let place = self.place(target)?;
+        let result = match op {
+            Some(op) => {
+                let current = self.load(&place, target.span)?;
+                let value = (self.eval_expr(value)?, value.span);
+                self.arithmetic(op, (current, target.span), value, span)?
+            }
+            None => self.eval_expr(value)?,
+        };
+        self.assign_value(&place, result, target.span, span)
+    }
+
+    /// Stores what an assignment computed. Yields the stored value.
+    pub(crate) fn assign_value(
+        &mut self,
+        place: &Place,
+        result: Value,
+        target_span: Span,
+        span: Span,
+    ) -> Result<Value, Error> {
+        if result == Value::Void {
+            return Err(Error::new(
+                ErrorCode::TypeMismatch,
+                span,
+                "Unable to resolve r-value",
+            ));
+        }
+        self.store(place, result, target_span)
+    }
+
+    /**
+     * Prefix and postfix `++`/`--`. Prefix yields the updated value, postfix
+     * the value the variable held before.
+     */
+    pub(crate) fn increment(
+        &mut self,
+        op: UnaryOp,
+        target: &ExprNode,
+        span: Span,
+    ) -> Result<Value, Error> {
+        let place = self.place(target)?;
+        self.step(op, &place, target.span, span)
+    }
+
+    /// Increments or decrements what is stored at `place`, which `target_span` names.
+    pub(crate) fn step(
+        &mut self,
+        op: UnaryOp,
+        place: &Place,
+        target_span: Span,
+        span: Span,
+    ) -> Result<Value, Error> {
+        let old = self.load(place, target_span)?;
+        let one = match old {
+            Value::Float(_) => Value::Float(1.0),
+            _ => Value::Int(1),
+        };
+        let step = match op {
+            UnaryOp::PREINC | UnaryOp::POSTINC => BinOp::Add(AddOp::PLUS),
+            _ => BinOp::Add(AddOp::MINUS),
+        };
+        let new = self.arithmetic(step, (old.clone(), target_span), (one, span), span)?;
+        let new = self.store(place, new, target_span)?;
+        match op {
+            UnaryOp::POSTINC | UnaryOp::POSTDEC => Ok(old),
+            _ => Ok(new),
+        }
+    }
+
+    pub(crate) fn unary(
+        &mut self,
+        op: UnaryOp,
+        operand: &ExprNode,
+        span: Span,
+    ) -> Result<Value, Error> {
+        match op {
+            UnaryOp::PREINC | UnaryOp::PREDEC | UnaryOp::POSTINC | UnaryOp::POSTDEC => {
+                return self.increment(op, operand, span)
+            }
+            UnaryOp::ADDR => return self.address_of(operand),
+            UnaryOp::DEREF => {
+                let value = self.eval_expr(operand)?;
+                let place = self.deref(value, operand.span)?;
+                return self.load(&place, span);
+            }
+            _ => {}
+        }
+        let value = self.eval_expr(operand)?;
+        self.unary_value(op, value, operand.span, span)
+    }
+
+    /// `!`, unary `-` or `+` on an evaluated operand.
+    pub(crate) fn unary_value(
+        &self,
+        op: UnaryOp,
+        value: Value,
+        operand_span: Span,
+        span: Span,
+    ) -> Result<Value, Error> {
+        match (op, as_int(value)) {
+            (UnaryOp::NOT, value) => Ok(Value::Bool(!self.is_truthy(&value, operand_span)?)),
+            (UnaryOp::MINUS, Value::Int(m)) => {
+                Ok(Value::Int(self.overflow.fit(-i64::from(m), span)?))
+            }
+            (UnaryOp::MINUS, Value::Float(m)) => Ok(Value::Float(-m)),
+            (_, value @ Value::Int(_)) | (_, value @ Value::Float(_)) => Ok(value),
+            _ => Err(Error::new(
+                ErrorCode::TypeMismatch,
+                span,
+                "Operand of unary + or - must be an int or a float",
+            )),
+        }
+    }
+
+    /**
+     * Arithmetic on two evaluated operands, each paired with its location.
+     * Two ints stay an int; if either side is a float the other is promoted
+     * and the result is a float.
+     */
+    pub(crate) fn arithmetic(
+        &self,
+        op: BinOp,
+        (lhs, lhs_span): (Value, Span),
+        (rhs, rhs_span): (Value, Span),
+        span: Span,
+    ) -> Result<Value, Error> {
+        if is_pointer(&lhs) || is_pointer(&rhs) {
+            return pointer_arithmetic(op, lhs, rhs, span);
+        }
+        match (&lhs, &rhs, op) {
+            (Value::Str(m), Value::Str(n), BinOp::Add(AddOp::PLUS)) => {
+                return Ok(Value::Str(format!("{}{}", m, n)))
+            }
+            (Value::Str(m), Value::Char(n), BinOp::Add(AddOp::PLUS)) => {
+                return Ok(Value::Str(format!("{}{}", m, n)))
+            }
+            _ => {}
+        }
+        let (lhs, rhs) = (as_int(lhs), as_int(rhs));
+        if let (Value::Int(m), Value::Int(n)) = (&lhs, &rhs) {
+            // Exact in i64, so only the final fit can overflow.
+            let (m, n) = (i64::from(*m), i64::from(*n));
+            if n == 0 {
+                if let BinOp::Mul(MulOp::DIV) | BinOp::Mul(MulOp::MODU) = op {
+                    return Err(Error::new(
+                        ErrorCode::DivisionByZero,
+                        span,
+                        "Division by zero",
+                    ));
+                }
+            }
+            let exact = match op {
+                BinOp::Add(AddOp::PLUS) => m + n,
+                BinOp::Add(AddOp::MINUS) => m - n,
+                BinOp::Mul(MulOp::MULT) => m * n,
+                BinOp::Mul(MulOp::DIV) => m / n,
+                BinOp::Mul(MulOp::MODU) => m % n,
+                _ => {
+                    return Err(Error::new(
+                        ErrorCode::Internal,
+                        span,
+                        "Unkown interpreting error - unexpected operations",
+                    ))
+                }
+            };
+            return Ok(Value::Int(self.overflow.fit(exact, span)?));
+        }
+        match (as_float(&lhs), as_float(&rhs)) {
+            (Some(m), Some(n)) => match op {
+                BinOp::Add(AddOp::PLUS) => Ok(Value::Float(m + n)),
+                BinOp::Add(AddOp::MINUS) => Ok(Value::Float(m - n)),
+                BinOp::Mul(MulOp::MULT) => Ok(Value::Float(m * n)),
+                BinOp::Mul(MulOp::DIV) => Ok(Value::Float(m / n)),
+                BinOp::Mul(MulOp::MODU) => Ok(Value::Float(m % n)),
+                _ => Err(Error::new(
+                    ErrorCode::Internal,
+                    span,
+                    "Unkown interpreting error - unexpected operations",
+                )),
+            },
+            (Some(_), None) => Err(Error::new(
+                ErrorCode::TypeMismatch,
+                rhs_span,
+                "R-value must be float or digit item",
+            )),
+            (None, _) => Err(Error::new(
+                ErrorCode::TypeMismatch,
+                lhs_span,
+                "L-value must be float or digit item",
+            )),
+        }
+    }
+
+    /**
+     * Explicit conversion. Float to int truncates toward zero, saturating at
+     * the int range; NaN becomes 0.
+     */
+    pub(crate) fn cast(
+        &mut self,
+        target: &Type,
+        operand: &ExprNode,
+        span: Span,
+    ) -> Result<Value, Error> {
+        let value = self.eval_expr(operand)?;
+        self.cast_value(target, value, operand.span, span)
+    }
+
+    /// Converts an evaluated operand, which `operand_span` locates, to `target`.
+    pub(crate) fn cast_value(
+        &self,
+        target: &Type,
+        value: Value,
+        operand_span: Span,
+        span: Span,
+    ) -> Result<Value, Error> {
+        match (target, &value) {
+            (Type::BOOL, _) => Ok(Value::Bool(self.is_truthy(&value, operand_span)?)),
+            (Type::CHAR, Value::Char(_)) => Ok(value),
+            (Type::CHAR, Value::Int(n)) => char_from(*n).map(Value::Char).ok_or_else(|| {
+                Error::new(
+                    ErrorCode::InvalidCast,
+                    span,
+                    format!("{} is not a char code", n),
+                )
+            }),
+            (Type::INT, Value::Char(_)) => Ok(as_int(value)),
+            // Like C, the fraction is dropped; unlike C, a value no int holds is an error.
+            (Type::INT, Value::Float(n)) => {
+                let n = n.trunc();
+                if n.is_nan() || n < f64::from(i32::MIN) || n > f64::from(i32::MAX) {
+                    return Err(Error::new(
+                        ErrorCode::InvalidCast,
+                        span,
+                        format!("{:?} does not fit in an int", value),
+                    ));
+                }
+                Ok(Value::Int(n as i32))
+            }
+            (Type::INT, Value::Bool(b)) => Ok(Value::Int(*b as i32)),
+            (Type::INT, Value::Int(_)) => Ok(value),
+            (Type::FLOAT, Value::Bool(b)) => Ok(Value::Float(f64::from(*b as i32))),
+            (Type::FLOAT, _) if as_float(&value).is_some() => {
+                Ok(Value::Float(as_float(&value).unwrap()))
+            }
+            _ => Err(Error::new(
+                ErrorCode::TypeMismatch,
+                span,
+                format!("Cannot cast {:?} to {:?}", value, target),
+            )),
+        }
+    }
+    /**
+     * Compares two evaluated operands. Ints, floats, chars and strings
+     * support all six operators, bools only equality. Strings compare by char.
+     * Pointers are equal when they point to the same cell, and ordered only
+     * within one array.
+     */
+    pub(crate) fn compare(
+        &self,
+        op: Compare,
+        lhs: Value,
+        rhs: Value,
+        span: Span,
+    ) -> Result<Value, Error> {
+        let (lhs, rhs) = (as_int(lhs), as_int(rhs));
+        let ordering = match (&lhs, &rhs) {
+            (Value::Int(m), Value::Int(n)) => m.partial_cmp(n),
+            (Value::Float(_), Value::Float(_))
+            | (Value::Int(_), Value::Float(_))
+            | (Value::Float(_), Value::Int(_)) => as_float(&lhs).partial_cmp(&as_float(&rhs)),
+            (Value::Str(m), Value::Str(n)) => m.partial_cmp(n),
+            (Value::Ptr(_, Some(m)), Value::Ptr(_, Some(n))) if Rc::ptr_eq(&m.block, &n.block) => {
+                m.offset.partial_cmp(&n.offset)
+            }
+            (Value::Ptr(_, m), Value::Ptr(_, n)) => match op {
+                Compare::EQ => return Ok(Value::Bool(m == n)),
+                Compare::NE => return Ok(Value::Bool(m != n)),
+                _ => {
+                    return Err(Error::new(
+                        ErrorCode::TypeMismatch,
+                        span,
+                        "Only pointers into the same array can be ordered",
+                    ))
+                }
+            },
+            (Value::Bool(m), Value::Bool(n)) => match op {
+                Compare::EQ => return Ok(Value::Bool(m == n)),
+                Compare::NE => return Ok(Value::Bool(m != n)),
+                _ => {
+                    return Err(Error::new(
+                        ErrorCode::TypeMismatch,
+                        span,
+                        "bools can only be compared with == and !=",
+                    ))
+                }
+            },
+            _ => {
+                return Err(Error::new(
+                    ErrorCode::TypeMismatch,
+                    span,
+                    format!("Cannot compare {:?} with {:?}", lhs, rhs),
+                ))
+            }
+        };
+
+        // NaN is unordered: every comparison with it is false, except !=.
+        let result = match op {
+            Compare::LT => ordering == Some(Ordering::Less),
+            Compare::GT => ordering == Some(Ordering::Greater),
+            Compare::LE => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+            Compare::GE => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+            Compare::EQ => ordering == Some(Ordering::Equal),
+            Compare::NE => ordering != Some(Ordering::Equal),
+        };
+        Ok(Value::Bool(result))
+    }
+
+    /// An index, which must be an int.
+    pub(crate) fn int_index(&self, index: Value, span: Span) -> Result<i32, Error> {
+        match index {
+            Value::Int(n) => Ok(n),
+            other => Err(Error::new(
+                ErrorCode::TypeMismatch,
+                span,
+                format!("Index must be an int, found {:?}", other.get_type()),
+            )),
+        }
+    }
+
+    /// An index checked against the length of what it indexes.
+    pub(crate) fn position(&self, index: Value, span: Span, len: usize) -> Result<usize, Error> {
+        let position = self.int_index(index, span)?;
+        match usize::try_from(position) {
+            Ok(i) if i < len => Ok(i),
+            _ => Err(Error::new(
+                ErrorCode::IndexOutOfBounds,
+                span,
+                format!("Index {} out of bounds for length {}", position, len),
+            )),
+        }
+    }
+
+    /**
+     * Checks that `value` can be indexed before its index is evaluated: it
+     * is a live array, a pointer, or a string when `strings` is set.
+     */
+    pub(crate) fn indexable(&self, value: &Value, span: Span, strings: bool) -> Result<(), Error> {
+        match value {
+            Value::Array(_, block) if !block.is_live() => Err(Error::new(
+                ErrorCode::DanglingPointer,
+                span,
+                "Array that is no longer live",
+            )),
+            Value::Array(..) | Value::Ptr(..) => Ok(()),
+            Value::Str(_) if strings => Ok(()),
+            other => Err(Error::new(
+                ErrorCode::TypeMismatch,
+                span,
+                format!("Cannot index {:?}", other.get_type()),
+            )),
+        }
+    }
+
+    /**
+     * The element `index` of an array, or the cell `index` past where a
+     * pointer points, so `p[i]` is `*(p + i)`.
+     */
+    pub(crate) fn element<'a>(
+        &mut self,
+        value: Value,
+        value_span: Span,
+        index: &ExprNode,
+    ) -> Result<Place<'a>, Error> {
+        self.indexable(&value, value_span, false)?;
+        let position = self.eval_expr(index)?;
+        self.element_at(value, value_span, (position, index.span))
+    }
+
+    /// `element` with the index evaluated.
+    pub(crate) fn element_at<'a>(
+        &mut self,
+        value: Value,
+        value_span: Span,
+        (index, index_span): (Value, Span),
+    ) -> Result<Place<'a>, Error> {
+        match value {
+            Value::Array(elem, block) => {
+                let i = self.position(index, index_span, block.len())?;
+                Ok(Place::Cell(elem, Pointer::new(block, i as isize)))
+            }
+            Value::Ptr(target, pointer) => {
+                let n = self.int_index(index, index_span)?;
+                let pointer = pointer.map(|pointer| pointer.add(n as isize));
+                self.deref(Value::Ptr(target, pointer), value_span)
+            }
+            other => Err(Error::new(
+                ErrorCode::TypeMismatch,
+                value_span,
+                format!("Cannot index {:?}", other.get_type()),
+            )),
+        }
+    }
+
+    /**
+     * `value[index]`: the char of a string, the element of an array or the
+     * cell after a pointer at a position counted from 0.
+     */
+    pub(crate) fn index(&mut self, value: &ExprNode, index: &ExprNode) -> Result<Value, Error> {
+        let indexed = self.eval_expr(value)?;
+        self.indexable(&indexed, value.span, true)?;
+        let position = self.eval_expr(index)?;
+        self.index_value(indexed, value.span, (position, index.span))
+    }
+
+    /// `index` with both sides evaluated.
+    pub(crate) fn index_value(
+        &mut self,
+        value: Value,
+        value_span: Span,
+        (index, index_span): (Value, Span),
+    ) -> Result<Value, Error> {
+        match value {
+            Value::Str(s) => {
+                let i = self.position(index, index_span, s.chars().count())?;
+                Ok(Value::Char(s.chars().nth(i).unwrap()))
+            }
+            indexed => {
+                let place = self.element_at(indexed, value_span, (index, index_span))?;
+                self.load(&place, index_span)
+            }
+        }
+    }
+
+    /**
+     * An array initializer: `len` elements of type `elem`, the first ones
+     * from `items` and the rest zero.
+     */
+    pub(crate) fn array(
+        &mut self,
+        elem: &Type,
+        len: usize,
+        items: &[ExprNode],
+        span: Span,
+    ) -> Result<Value, Error> {
+        let mut values = Vec::with_capacity(items.len());
+        for item in items {
+            let value = self.eval_expr(item)?;
+            values.push(array_item(elem, value, item.span)?);
+        }
+        let cells = self.fill(elem, len, values, span)?;
+        let block = self.allocate(cells);
+        Ok(Value::Array(elem.clone(), block))
+    }
+
+    /// The cells of an array of `len` elements: `values`, then zeros.
+    pub(crate) fn fill(
+        &self,
+        elem: &Type,
+        len: usize,
+        mut values: Vec<Value>,
+        span: Span,
+    ) -> Result<Vec<Option<Value>>, Error> {
+        if len > MAX_ARRAY_LEN {
+            return Err(Error::new(
+                ErrorCode::Unsupported,
+                span,
+                format!("Arrays can have at most {} elements", MAX_ARRAY_LEN),
+            ));
+        }
+        if values.len() < len {
+            let fill = self.zero(elem).ok_or_else(|| {
+                Error::new(
+                    ErrorCode::Unsupported,
+                    span,
+                    format!("Arrays of {:?} need an initializer for every element", elem),
+                )
+            })?;
+            values.resize(len, fill);
+        }
+        Ok(values.into_iter().map(Some).collect())
+    }
+
+    /// What a variable of type `var_type` starts as when it must have a value.
+    pub(crate) fn zero(&self, var_type: &Type) -> Option<Value> {
+        match var_type {
+            Type::INT => Some(Value::Int(0)),
+            Type::FLOAT => Some(Value::Float(0.0)),
+            Type::BOOL => Some(Value::Bool(false)),
+            Type::CHAR => Some(Value::Char('\0')),
+            Type::STRING => Some(Value::Str(String::new())),
+            Type::POINTER(target) => Some(Value::Ptr((**target).clone(), None)),
+            Type::STRUCT(name) => {
+                let decl = self.structs.get(name)?;
+                let fields = decl
+                    .fields
+                    .iter()
+                    .map(|(field_type, _)| self.zero(field_type))
+                    .collect::<Option<_>>()?;
+                Some(Value::Struct(Rc::clone(decl), fields))
+            }
+            _ => None,
+        }
+    }
+
+    /// Checks that the struct types `var_type` mentions are declared.
+    pub(crate) fn check_type(&self, var_type: &Type, span: Span) -> Result<(), Error> {
+        match var_type {
+            Type::ARRAY(elem) => self.check_type(elem, span),
+            Type::STRUCT(name) if !self.structs.contains_key(name) => Err(Error::new(
+                ErrorCode::UnknownType,
+                span,
+                format!("struct {} is not declared", name),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /**
+     * Makes a struct type usable. Its field types must already be declared, so
+     * a struct can't contain itself. Running the same declaration again, as a
+     * function declaring a struct does on every call, is allowed.
+     */
+    pub(crate) fn declare_struct(
+        &mut self,
+        decl: &Rc<StructDecl>,
+        span: Span,
+    ) -> Result<(), Error> {
+        if let Some(existing) = self.structs.get(&decl.name) {
+            if existing == decl {
+                return Ok(());
+            }
+            return Err(Error::new(
+                ErrorCode::Redeclaration,
+                span,
+                format!("struct {} is already declared", decl.name),
+            ));
+        }
+        for (field_type, _) in &decl.fields {
+            self.check_type(field_type, span)?;
+        }
+        self.structs.insert(decl.name.clone(), Rc::clone(decl));
+        Ok(())
+    }
+
+    /// Where `field` sits in the struct `value`.
+    pub(crate) fn field_position(
+        &self,
+        value: &Value,
+        field: &str,
+        span: Span,
+    ) -> Result<usize, Error> {
+        match value {
+            Value::Struct(decl, _) => decl
+                .fields
+                .iter()
+                .position(|(_, name)| name == field)
+                .ok_or_else(|| {
+                    Error::new(
+                        ErrorCode::UnknownField,
+                        span,
+                        format!("struct {} has no field {}", decl.name, field),
+                    )
+                }),
+            other => Err(Error::new(
+                ErrorCode::TypeMismatch,
+                span,
+                format!("{:?} has no fields", other.get_type()),
+            )),
+        }
+    }
+
+    /// `value.field`.
+    pub(crate) fn field(
+        &mut self,
+        value: &ExprNode,
+        field: &str,
+        span: Span,
+    ) -> Result<Value, Error> {
+        let value = self.eval_expr(value)?;
+        self.field_value(value, field, span)
+    }
+
+    /// `field` of an evaluated struct.
+    pub(crate) fn field_value(
+        &self,
+        value: Value,
+        field: &str,
+        span: Span,
+    ) -> Result<Value, Error> {
+        let i = self.field_position(&value, field, span)?;
+        match value {
+            Value::Struct(_, mut fields) => Ok(fields.swap_remove(i)),
+            _ => unreachable!("field_position only accepts structs"),
+        }
+    }
+
+    /**
+     * A struct initializer: the fields of struct `name` in order, the first
+     * ones from `items` and the rest zero.
+     */
+    pub(crate) fn struct_value(
+        &mut self,
+        name: &str,
+        items: &[ExprNode],
+        span: Span,
+    ) -> Result<Value, Error> {
+        let decl = self.struct_decl(name, items.len(), span)?;
+        let mut fields = Vec::with_capacity(decl.fields.len());
+        for (i, item) in items.iter().enumerate() {
+            let value = self.eval_expr(item)?;
+            fields.push(struct_field(&decl, i, value, item.span)?);
+        }
+        self.struct_rest(decl, fields, span)
+    }
+
+    /// The struct `name`, checked to have room for `count` initializers.
+    pub(crate) fn struct_decl(
+        &self,
+        name: &str,
+        count: usize,
+        span: Span,
+    ) -> Result<Rc<StructDecl>, Error> {
+        let var_type = Type::STRUCT(name.to_string());
+        self.check_type(&var_type, span)?;
+        let decl = Rc::clone(&self.structs[name]);
+        if count > decl.fields.len() {
+            return Err(Error::new(
+                ErrorCode::TypeMismatch,
+                span,
+                format!(
+                    "struct {} has {} fields, {} given",
+                    name,
+                    decl.fields.len(),
+                    count
+                ),
+            ));
+        }
+        Ok(decl)
+    }
+
+    /// A struct from its first `fields`, with the rest zero.
+    pub(crate) fn struct_rest(
+        &self,
+        decl: Rc<StructDecl>,
+        mut fields: Vec<Value>,
+        span: Span,
+    ) -> Result<Value, Error> {
+        for (field_type, field) in &decl.fields[fields.len()..] {
+            let value = self.zero(field_type).ok_or_else(|| {
+                Error::new(
+                    ErrorCode::Unsupported,
+                    span,
+                    format!("{}.{} needs an initializer", decl.name, field),
+                )
+            })?;
+            fields.push(value);
+        }
+        Ok(Value::Struct(decl, fields))
+    }
+
+    /**
+     * `&&` and `||` short-circuit: the right side only runs when the left
+     * side doesn't already decide the result.
+     */
+    pub(crate) fn logic(
+        &mut self,
+        op: Logic,
+        left: &ExprNode,
+        right: &ExprNode,
+    ) -> Result<Value, Error> {
+        match (op, self.condition(left)?) {
+            (Logic::AND, false) => Ok(Value::Bool(false)),
+            (Logic::OR, true) => Ok(Value::Bool(true)),
+            _ => Ok(Value::Bool(self.condition(right)?)),
+        }
+    }
+
+    pub(crate) fn binary(
+        &mut self,
+        op: BinOp,
+        left: &ExprNode,
+        right: &ExprNode,
+        span: Span,
+    ) -> Result<Value, Error> {
+        if let BinOp::Logic(op) = op {
+            return self.logic(op, left, right);
+        }
+        let lhs = self.eval_expr(left)?;
+        let rhs = self.eval_expr(right)?;
+        match op {
+            BinOp::Compare(op) => self.compare(op, lhs, rhs, span),
+            _ => self.arithmetic(op, (lhs, left.span), (rhs, right.span), span),
+        }
+    }
+
+    // purely lexical checking of types... or is it?
+    pub(crate) fn check_vars(
+        &mut self,
+        decl: &Rc<FnDecl>,
+        args: Vec<(Value, Span)>,
+        span: Span,
+    ) -> Result<(), Error> {
+        if args.len() != decl.params.len() {
+            return Err(Error::new(
+                ErrorCode::ArityMismatch,
+                span,
+                format!(
+                    "{} takes {} arguments, {} given",
+                    decl.name,
+                    decl.params.len(),
+                    args.len()
+                ),
+            ));
+        }
+        for ((param_type, param), (arg, arg_span)) in decl.params.iter().zip(args) {
+            let arg = check_arg(param, param_type, arg, arg_span)?;
+            self.declare_var(param.clone(), param_type.clone(), Some(arg), arg_span)?;
+        }
+        self.declare_var(
+            decl.name.clone(),
+            Type::FUNC,
+            Some(Value::Func(Rc::clone(decl))),
+            span,
+        )
+    }
+
+    /**
+     * Runs a host function. Natives have no scope of their own, so the checks
+     * `check_vars` does while binding parameters happen here up front.
+     */
+    pub(crate) fn call_native(
+        &mut self,
+        native: &NativeFn,
+        args: Vec<(Value, Span)>,
+        span: Span,
+    ) -> Result<Value, Error> {
+        if args.len() != native.params.len() {
+            return Err(Error::new(
+                ErrorCode::ArityMismatch,
+                span,
+                format!(
+                    "{} takes {} arguments, {} given",
+                    native.name,
+                    native.params.len(),
+                    args.len()
+                ),
+            ));
+        }
+        let mut values = Vec::with_capacity(args.len());
+        for (i, (param_type, (arg, arg_span))) in native.params.iter().zip(args).enumerate() {
+            let param = format!("{} argument {}", native.name, i + 1);
+            values.push(check_arg(&param, param_type, arg, arg_span)?);
+        }
+        let result = match &native.func {
+            Callback::Host(func) => func(&values).map_err(|message| {
+                Error::new(
+                    ErrorCode::NativeFailure,
+                    span,
+                    format!("{}: {}", native.name, message),
+                )
+            })?,
+            Callback::Builtin(func) => func(self, &values, span)?,
+        };
+        let result = promote(&native.ret, result);
+        if result.get_type() != native.ret && native.ret != Type::ANY {
+            return Err(Error::new(
+                ErrorCode::TypeMismatch,
+                span,
+                format!(
+                    "{} returned {:?}, declared {:?}",
+                    native.name,
+                    result.get_type(),
+                    native.ret
+                ),
+            ));
+        }
+        Ok(result)
+    }
+
+    pub(crate) fn find_fn(&mut self, name: &str, span: Span) -> Result<Value, Error> {
+        function(name, self.find_slot(name), span)
+    }
+
+    pub(crate) fn call_expr(
+        &mut self,
+        callee: &ExprNode,
+        args: &[ExprNode],
+        span: Span,
+    ) -> Result<Value, Error> {
+        let callee = match &callee.kind {
+            Expr::Var(name) => self.find_fn(name, span)?,
+            _ => {
+                let value = self.eval_expr(callee)?;
+                callable(value, span)?
+            }
+        };
+        // Arguments are evaluated in the caller's scope.
+        let mut values = Vec::new();
+        for arg in args {
+            values.push((self.eval_expr(arg)?, arg.span));
+        }
+        self.call_value(&callee, values, span)
+    }
+
+    pub(crate) fn call_value(
+        &mut self,
+        callee: &Value,
+        args: Vec<(Value, Span)>,
+        span: Span,
+    ) -> Result<Value, Error> {
+        let (decl, captures) = match callee {
+            Value::Func(decl) => (decl, &[][..]),
+            Value::Closure(closure) => (&closure.decl, &closure.captures[..]),
+            Value::Native(native) => return self.call_native(native, args, span),
+            _ => {
+                return Err(Error::new(
+                    ErrorCode::Internal,
+                    span,
+                    format!("{:?} is not callable", callee),
+                ))
+            }
+        };
+        if self.depth >= self.max_call_depth {
+            return Err(Error::new(
+                ErrorCode::StackOverflow,
+                span,
+                format!(
+                    "stack overflow: more than {} nested calls",
+                    self.max_call_depth
+                ),
+            ));
+        }
+        self.enter_frame();
+        let result = stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT, || {
+            self.check_vars(decl, args, span)?;
+            self.bind_captures(captures);
+            self.exec_block(&decl.body)
+        });
+        // The frame goes whether the body finished, returned or failed.
+        self.leave_frame();
+        let result = match result? {
+            Flow::Return(value) => value,
+            _ => Value::Void,
+        };
+        check_return(decl, result, span)
+    }
+
+    /// Makes a closure's captured variables visible in its frame. Its
+    /// parameters shadow them.
+    pub(crate) fn bind_captures(&mut self, captures: &[(String, Var)]) {
+        if let Some(scope) = self.scope.last_mut().and_then(|frame| frame.last_mut()) {
+            for (name, var) in captures {
+                scope.entry(name.clone()).or_insert_with(|| var.clone());
+            }
+        }
+    }
+
+    /**
+     * Evaluates a lambda into a closure. Every local the body names is
+     * captured, and escapes its scope so it lives as long as the closure.
+     * Globals aren't captured; as in named functions, they are looked up when
+     * the closure runs.
+     */
+    pub(crate) fn closure(&mut self, decl: &Rc<FnDecl>) -> Value {
+        let mut captures = Vec::new();
+        for name in decl.names() {
+            if let Some(var) = self.find_local(&name) {
+                self.memory.escape(&var.1);
+                captures.push((name, var));
+            }
+        }
+        Value::Closure(Rc::new(Closure {
+            decl: Rc::clone(decl),
+            captures,
+        }))
+    }
+
+    /// Opens the call frame of a script function, with a scope for its parameters.
+    pub(crate) fn enter_frame(&mut self) {
+        self.depth += 1;
+        self.scope.push(Vec::new());
+        self.push_scope();
+    }
+
+    /// Closes the innermost call frame, releasing everything allocated in it.
+    pub(crate) fn leave_frame(&mut self) {
+        self.pop_scope();
+        self.scope.pop();
+        self.depth -= 1;
+    }
+
+    pub(crate) fn eval_expr(&mut self, input: &ExprNode) -> Result<Value, Error> {
+        match &input.kind {
+            Expr::Int(n) => Ok(Value::Int(*n)),
+            Expr::Float(n) => Ok(Value::Float(*n)),
+            Expr::Bool(b) => Ok(Value::Bool(*b)),
+            Expr::Str(s) => Ok(Value::Str(s.clone())),
+            Expr::Char(c) => Ok(Value::Char(*c)),
+            Expr::Null => Ok(Value::Ptr(Type::NONE, None)),
+            Expr::Var(name) => self.load_var(name, input.span),
+            Expr::Call(callee, args) => self.call_expr(callee, args, input.span),
+            Expr::Lambda(decl) => Ok(self.closure(decl)),
+            Expr::Unary(op, operand) => self.unary(*op, operand, input.span),
+            Expr::Binary(op, left, right) => self.binary(*op, left, right, input.span),
+            Expr::Assign(op, target, value) => self.assign(*op, target, value, input.span),
+            Expr::Cast(target, operand) => self.cast(target, operand, input.span),
+            Expr::Index(value, index) => self.index(value, index),
+            Expr::Array(elem, len, items) => self.array(elem, *len, items, input.span),
+            Expr::Field(value, field) => self.field(value, field, input.span),
+            Expr::Struct(name, items) => self.struct_value(name, items, input.span),
+        }
+    }
+
+    /// What the checker knows about each global before a program runs.
+    pub(crate) fn symbols(&self) -> HashMap<String, Symbol> {
+        let symbol = |(var_type, block): &Var| match block.read(0) {
+            Some(Value::Func(decl)) => Symbol::Func(Signature::of(&decl)),
+            Some(Value::Native(native)) => Symbol::Func(Signature {
+                params: native
+                    .params
+                    .iter()
+                    .enumerate()
+                    .map(|(i, param)| {
+                        (param.clone(), format!("{} argument {}", native.name, i + 1))
+                    })
+                    .collect(),
+                ret: native.ret.clone(),
+            }),
+            _ => Symbol::Var(var_type.clone()),
+        };
+        self.global_vars
+            .iter()
+            .map(|(name, var)| (name.clone(), symbol(var)))
+            .collect()
+    }
+
+    /**
+     * Checks `program` against the globals it would run with, without running
+     * it: undeclared names, call arity, and the types of operands, assignments
+     * and returns. Every error found is returned, in source order.
+     */
+    pub fn check(&self, program: &Program) -> Result<(), Vec<Error>> {
+        let mut symbols = self.symbols();
+        for name in self.rerun_globals(program) {
+            symbols.remove(name);
+        }
+        Checker::new(symbols, self.structs.clone()).check(&program.body)
+    }
+
+    /// The globals an earlier run of `program` declared, which a new run replaces.
+    fn rerun_globals<'a>(&self, program: &'a Program) -> Vec<&'a str> {
+        if self.ran.contains(program) {
+            program.globals()
+        } else {
+            Vec::new()
+        }
+    }
+
+    /**
+     * Parses, checks and runs `source`, a braced program such as
+     * `{ int a = 2; a * 3 }`, and returns the value it ends with. Only the
+     * first error `check` finds is returned.
+     */
+    pub fn eval(&mut self, source: &str) -> Result<Value, Error> {
+        let program = Program::parse(source)?;
+        self.check(&program)
+            .map_err(|mut errors| errors.remove(0))?;
+        self.run(&program)
+    }
+
+    /**
+     * Runs a single statement: the REPL's unit of work. An expression
+     * statement evaluates to its value, so `1 + 2` gives `Int(3)`.
+     */
+    pub fn eval_statement(&mut self, source: &str) -> Result<Value, Error> {
+        let statement = Parser::new(source)?.statement()?;
+        Checker::new(self.symbols(), self.structs.clone())
+            .check(std::slice::from_ref(&statement))
+            .map_err(|mut errors| errors.remove(0))?;
+        if self.engine == Engine::Vm {
+            return self.run_chunk(Compiler::statement_chunk(&statement));
+        }
+        self.scope.push(Vec::new());
+        let res = match &statement.kind {
+            Stmt::Expr(expr) => self.eval_expr(expr),
+            _ => self.exec(&statement).map(|flow| match flow {
+                Flow::Return(value) => value,
+                _ => Value::Void,
+            }),
+        };
+        self.scope.pop();
+        res
+    }
+
+    /**
+     * Runs a parsed program. Its top level declarations become globals, so
+     * they outlive the run: later runs, `get_global` and `call` all see them.
+     * Running the same program again starts its own globals over instead of
+     * redeclaring them. `run` doesn't check the program: call `check` first
+     * and run only when it passes, otherwise errors the checker would have
+     * reported together surface one at a time, after some of the program ran.
+     */
+    pub fn run(&mut self, program: &Program) -> Result<Value, Error> {
+        for name in self.rerun_globals(program) {
+            self.global_vars.remove(name);
+        }
+        if !program.globals().is_empty() && !self.ran.contains(program) {
+            self.ran.push(program.clone());
+        }
+        if self.engine == Engine::Vm {
+            return self.run_chunk(Compiler::program(&program.body));
+        }
+        self.scope.push(Vec::new());
+        let mut result = Ok(Flow::Normal);
+        for statement in &program.body {
+            match self.exec(statement) {
+                Ok(Flow::Normal) => {}
+                other => {
+                    result = other;
+                    break;
+                }
+            }
+        }
+        self.scope.pop();
+        match result? {
+            Flow::Return(value) => Ok(value),
+            _ => Ok(Value::Void),
+        }
+    }
+
+    /// The value of a global, if it is declared and initialized.
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        self.global_vars
+            .get(name)
+            .and_then(|(_, block)| block.read(0))
+    }
+
+    /**
+     * Sets a global, declaring it with the value's type when it doesn't exist.
+     * An existing global keeps its type: an int is promoted into a float
+     * global, any other mismatch is a type error.
+     */
+    pub fn set_global(&mut self, name: &str, value: Value) -> Result<(), Error> {
+        match self.global_vars.get_mut(name) {
+            Some((var_type, block)) => {
+                let value = promote(var_type, value);
+                if value.get_type() != *var_type {
+                    return Err(Error::new(
+                        ErrorCode::TypeMismatch,
+                        Span::default(),
+                        format!(
+                            "{} is of incorrect type: Should be {:?}, is {:?}",
+                            name,
+                            var_type,
+                            value.get_type()
+                        ),
+                    ));
+                }
+                block.write(0, value);
+            }
+            None => {
+                let block = self
+                    .memory
+                    .allocate(vec![Some(value.clone())], Region::Global);
+                self.global_vars
+                    .insert(name.to_string(), (value.get_type(), block));
+            }
+        }
+        Ok(())
+    }
+
+    /**
+     * Calls the script function `name` with `args`, which are checked against
+     * its parameters the same way a call from a script is.
+     */
+    pub fn call(&mut self, name: &str, args: &[Value]) -> Result<Value, Error> {
+        let span = Span::default();
+        let callee = self.find_fn(name, span)?;
+        let args = args.iter().map(|arg| (arg.clone(), span)).collect();
+        match self.engine {
+            Engine::Tree => self.call_value(&callee, args, span),
+            Engine::Vm => self.call_compiled(&callee, args, span),
+        }
+    }
+
+    /**
+     * Makes the Rust function `func` callable from scripts as `name`. Calls are
+     * checked against `params` and `ret` like calls to script functions; an
+     * `Err` returned by `func` becomes a runtime error at the call.
+     */
+    pub fn register_fn<F>(&mut self, name: &str, params: &[Type], ret: Type, func: F)
+    where
+        F: Fn(&[Value]) -> Result<Value, String> + 'static,
+    {
+        self.register(NativeFn {
+            name: name.to_string(),
+            params: params.to_vec(),
+            ret,
+            func: Callback::Host(Box::new(func)),
+        });
+    }
+
+    /// Like `register_fn`, for prelude functions that need the interpreter.
+    pub(crate) fn register_builtin(
+        &mut self,
+        name: &str,
+        params: &[Type],
+        ret: Type,
+        func: Builtin,
+    ) {
+        self.register(NativeFn {
+            name: name.to_string(),
+            params: params.to_vec(),
+            ret,
+            func: Callback::Builtin(func),
+        });
+    }
+
+    pub(crate) fn register(&mut self, native: NativeFn) {
+        let name = native.name.clone();
+        let value = Value::Native(Rc::new(native));
+        let block = self.memory.allocate(vec![Some(value)], Region::Global);
+        self.global_vars.insert(name, (Type::FUNC, block));
+    }
+
+    /**
+     * `len` cells on the heap for `malloc` and `calloc`, as a void pointer.
+     * `span` is the call, which a leak report points at.
+     */
+    pub(crate) fn malloc(&mut self, len: usize, zeroed: bool, span: Span) -> Result<Value, Error> {
+        let block = self.memory.malloc(len, zeroed, span)?;
+        Ok(Value::Ptr(Type::NONE, Some(Pointer::new(block, 0))))
+    }
+
+    /// `free`: releases what `pointer` points to. Freeing `NULL` does nothing.
+    pub(crate) fn free(&mut self, pointer: &Value, span: Span) -> Result<(), Error> {
+        match pointer {
+            Value::Ptr(_, Some(pointer)) => self.memory.free(pointer, span),
+            _ => Ok(()),
+        }
+    }
+
+    /**
+     * The heap allocations that are still live: everything `malloc` or
+     * `calloc` returned that was never freed, oldest first.
+     */
+    pub fn leaks(&self) -> Vec<Leak> {
+        self.memory.leaks()
+    }
+
+    /**
+     * A report of `leaks`, one `file:line:col` line per allocation and a
+     * total, or `None` when every allocation was freed.
+     */
+    pub fn leak_report(&self, file: &str) -> Option<String> {
+        let leaks = self.leaks();
+        if leaks.is_empty() {
+            return None;
+        }
+        let mut report = String::new();
+        for leak in &leaks {
+            report.push_str(&format!(
+                "{}:{}:{}: {} allocated here never freed\n",
+                file,
+                leak.span.line,
+                leak.span.col,
+                cells(leak.cells)
+            ));
+        }
+        let total = leaks.iter().map(|leak| leak.cells).sum();
+        report.push_str(&format!(
+            "leaked {} in {} allocation{}",
+            cells(total),
+            leaks.len(),
+            if leaks.len() == 1 { "" } else { "s" }
+        ));
+        Some(report)
+    }
+}
+
+// INTERPRETER TESTS
+
+#[cfg(test)]
+mod interp_test {
+    #[allow(unused_imports)]
+    use super::*;
+    use crate::translator::Translator;
+
+    #[test]
+    fn interp_basic_add_float() {
+        assert_eq!(
+            Value::Float(1.4 + 2.3),
+            Interpreter::new().eval_statement("1.4+2.3")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn interp_basic_add() {
+        assert_eq!(
+            Value::Int(3),
+            Interpreter::new().eval_statement("1+2").unwrap()
+        );
+    }
+
+    #[test]
+    fn interp_unary_minus() {
+        assert_eq!(
+            Value::Int(3),
+            Interpreter::new().eval_statement("- -3").unwrap()
+        );
+    }
+
+    #[test]
+    fn interp_unary_plus() {
+        assert_eq!(
+            Value::Int(3),
+            Interpreter::new().eval_statement("+ +3").unwrap()
+        );
+    }
+
+    #[test]
+    fn interp_unary_both() {
+        assert_eq!(
+            Value::Int(3),
+            Interpreter::new().eval_statement("-+-3").unwrap()
+        );
+    }
+
+    #[test]
+    fn interp_chain_add() {
+        assert_eq!(
+            Value::Int(6),
+            Interpreter::new().eval_statement("1+2+3")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn interp_precedence_test() {
+        assert_eq!(
+            Value::Int(7),
+            Interpreter::new().eval_statement("1+2*3")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn interp_precedence_test2() {
+        assert_eq!(
+            Value::Int(5),
+            Interpreter::new().eval_statement("1*2+3")
+                .unwrap()
+        );
+    }
+    #[test]
+    fn interp_parentheses_test() {
+        assert_eq!(
+            Value::Int(9),
+            Interpreter::new().eval_statement("(1+2)*3")
+                .unwrap()
+        );
+    }
+    #[test]
+    fn interp_basic_interp_plus() {
+        assert_eq!(
+            Value::Int(3),
+            Interpreter::new().eval_statement("1+2").unwrap()
+        );
+    }
+
+    #[test]
+    fn interp_basic_interp_minus() {
+        assert_eq!(
+            Value::Int(1),
+            Interpreter::new().eval_statement("2-1").unwrap()
+        );
+    }
+
+    #[test]
+    fn interp_basic_interp_times() {
+        assert_eq!(
+            Value::Int(6),
+            Interpreter::new().eval_statement("2*3").unwrap()
+        );
+    }
+
+    #[test]
+    fn interp_basic_interp_divide() {
+        assert_eq!(
+            Value::Int(0),
+            Interpreter::new().eval_statement("2/3").unwrap()
+        );
+    }
+
+    #[test]
+    fn interp_basic_interp_modulo() {
+        assert_eq!(
+            Value::Int(2),
+            Interpreter::new().eval_statement("2%3").unwrap()
+        );
+    }
+    #[test]
+    fn interp_test_vars() {
+        assert_eq!(
+            Value::Int(3),
+            Interpreter::new().eval("{int a = 3; a}")
+                .unwrap()
+        )
+    }
+
+    #[test]
+    fn interp_rpn_translate() {
+        assert_eq!(
+            "1 2 +",
+            Translator::new("1+2").unwrap().rpn_translate().unwrap()
+        )
+    }
+
+    #[test]
+    fn interp_empty_block() {
+        assert_eq!(
+            Value::Void,
+            Interpreter::new().eval("{}").unwrap()
+        )
+    }
+
+    #[test]
+    fn interp_different_return_varibale() {
+        assert_eq!(
+            Value::Int(6),
+            Interpreter::new().eval(
+                "
+            {
+                int b = 3; 
+                b+3
+            }
+                "
+            )
+            .unwrap()
+        )
+    }
+
+    #[test]
+    fn interp_variable_test() {
+        assert_eq!(
+            Value::Int(8),
+            Interpreter::new().eval(
+                "
+            {
+                int b = 3; 
+                b = 5;
+                b+3
+            }
+                "
+            )
+            .unwrap()
+        )
+    }
+    #[test]
+    fn interp_final_variable_test2() {
+        assert_eq!(
+            Value::Int(14),
+            Interpreter::new().eval(
+                "
+            {
+                int b = 3; 
+                int a;
+                a = b+3;
+                b=5;
+                b+3+a
+            }
+                "
+            )
+            .unwrap()
+        )
+    }
+    #[test]
+    fn interp_scope_test1() {
+        assert_eq!(
+            Value::Int(3),
+            Interpreter::new().eval(
+                "
+            {
+                int b = 3; 
+                {
+                    //this is in a different scope
+                    int b = 2
+                }
+                b
+            }
+                "
+            )
+            .unwrap()
+        )
+    }
+
+    #[test]
+    fn interp_basic_function_dec() {
+        assert_eq! {
+            Value::Void,
+            Interpreter::new().eval("
+            {
+                fn returnThree()->int{
+                    3
+                }
+            }").unwrap()
+        }
+    }
+    #[test]
+    fn interp_basic_function_call() {
+        assert_eq! {
+            Value::Int(3),
+            Interpreter::new().eval("
+            {
+                fn returnThree()->int{
+                    3
+                }
+                returnThree()
+            }").unwrap()
+        }
+    }
+
+    #[test]
+    fn interp_function_vars() {
+        assert_eq!(
+            Value::Int(8),
+            Interpreter::new().eval(
+                "
+            {
+                fn returnThree()->int{
+                    int b = 3; 
+                    b = 5;
+                    b+3
+                }
+                returnThree()
+            }"
+            )
+            .unwrap()
+        )
+    }
+
+    #[test]
+    fn interp_function_args() {
+        let b = Interpreter::new().eval(
+            "
+        {
+            fn returnArg(int a)->int{
+               a
+            }
+            returnArg(3)
+        }",
+        )
+        .unwrap();
+        assert_eq!(Value::Int(3), b)
+    }
+
+    #[test]
+    fn interp_function_many_args() {
+        let mut interpreter = Interpreter::new();
+        assert_eq!(
+            Value::Float(7.0),
+            interpreter
+                .eval("{ fn mix(int a, float b, int c)->float { a * b + c } mix(2, 2.5, len(\"ab\")) }")
+                .unwrap()
+        );
+        assert_eq!(
+            Value::Str("bc".into()),
+            interpreter.eval("{ substr(\"abcd\", 1, 2) }").unwrap()
+        );
+        // Hosts calling in skip the checker, so arity is checked at the call too.
+        let err = interpreter.call("mix", &[Value::Int(1)]).unwrap_err();
+        assert_eq!(ErrorCode::ArityMismatch, err.code());
+        assert_eq!("mix takes 3 arguments, 1 given", err.message());
+        let err = interpreter.eval("{ mix(1, 2.0) }").unwrap_err();
+        assert_eq!(ErrorCode::ArityMismatch, err.code());
+    }
+
+    #[test]
+    #[should_panic]
+    fn interp_function_args_type_error() {
+        Interpreter::new().eval(
+            "
+        {
+            fn returnArg(int a)->int{
+               a
+            }
+            returnArg(3.5)
+        }",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn interp_error_location() {
+        let source = "{
+        int a = 1;
+        if(a){
+        return count + 1;
+        }
+    }";
+        let err = Interpreter::new().eval(source)
+            .unwrap_err();
+        assert_eq!(ErrorCode::UndeclaredVariable, err.code());
+        assert_eq!(
+            "test.c:4:16: [E0400] Interpreting Error: Variable Not Declared\n        return count + 1;\n               ^^^^^",
+            err.render(source, "test.c")
+        )
+    }
+
+    #[test]
+    fn interp_type_error() {
+        match Interpreter::new().eval_statement("1 + true") {
+            Err(Error::Type { code, span, .. }) => {
+                assert_eq!(ErrorCode::TypeMismatch, code);
+                assert_eq!(5, span.col);
+            }
+            other => panic!("expected a type error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn interp_recursion() {
+        let b = Interpreter::new().eval(
+            "
+        {
+            fn returnArg(int a)->int{
+               if(a == 1){
+                   return 1
+               }else{
+                   return returnArg(a-1)
+               }
+            }
+            returnArg(3)
+        }",
+        )
+        .unwrap();
+        assert_eq!(Value::Int(1), b)
+    }
+
+    #[test]
+    fn interp_function_values() {
+        let cases = vec![
+            (
+                "{ fn twice(fn f, int x)->int { f(f(x)) } fn inc(int n)->int { n + 1 } twice(inc, 1) }",
+                Value::Int(3),
+            ),
+            (
+                "{ fn pick(bool first)->fn { fn a()->int { 1 } fn b()->int { 2 } \
+                 if (first) { return a; } return b; } pick(false)() }",
+                Value::Int(2),
+            ),
+            ("{ fn f = sqrt; f(16.0) }", Value::Float(4.0)),
+            ("{ fn fs[2] = {abs, sqrt}; fs[0](-3) }", Value::Int(3)),
+            ("{ fn (int y)->int { y * y }(7) }", Value::Int(49)),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(Ok(expected), Interpreter::new().eval(source), "{}", source);
+        }
+        let err = Interpreter::new().eval("{ int n = 1; n(2) }").unwrap_err();
+        assert_eq!(ErrorCode::TypeMismatch, err.code());
+    }
+
+    #[test]
+    fn interp_closures() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .eval(
+                "{
+                fn counter()->fn {
+                    int count = 0;
+                    return fn ()->int { count += 1; return count; };
+                }
+                fn adder(int n)->fn { return fn (int x)->int { x + n }; }
+                fn c = counter();
+                fn d = counter();
+            }",
+            )
+            .unwrap();
+        // Each closure keeps its own `count` alive after counter returned.
+        assert_eq!(Ok(Value::Int(1)), interpreter.eval("{ c() }"));
+        assert_eq!(Ok(Value::Int(2)), interpreter.eval("{ c() }"));
+        assert_eq!(Ok(Value::Int(1)), interpreter.eval("{ d() }"));
+        assert_eq!(Ok(Value::Int(15)), interpreter.eval("{ adder(10)(5) }"));
+        assert_eq!("fn <lambda>", interpreter.get_global("c").unwrap().to_string());
+        // Captured variables are shared with the scope that declared them.
+        assert_eq!(
+            Ok(Value::Int(5)),
+            interpreter.eval("{ int x = 1; fn get = fn ()->int { x }; x = 5; get() }")
+        );
+        // A captured array outlives the call that declared it.
+        assert_eq!(
+            Ok(Value::Int(6)),
+            interpreter.eval(
+                "{ fn table()->fn { int a[3] = {4, 5, 6}; return fn (int i)->int { a[i] }; } \
+                 table()(2) }"
+            )
+        );
+        // Parameters shadow captured variables.
+        assert_eq!(
+            Ok(Value::Int(7)),
+            interpreter.eval("{ fn f()->fn { int x = 1; return fn (int x)->int { x }; } f()(7) }")
+        );
+    }
+
+    #[test]
+    fn interp_deep_recursion() {
+        let source = "{ fn depth(int n)->int { if (n == 0) { return 0; } return 1 + depth(n - 1); } }";
+        let mut interpreter = Interpreter::new();
+        interpreter.eval(source).unwrap();
+        // Far deeper than the native stack of a test thread would allow.
+        assert_eq!(
+            Ok(Value::Int(5000)),
+            interpreter.call("depth", &[Value::Int(5000)])
+        );
+        // depth(99) nests 100 calls, down to depth(0).
+        interpreter.set_max_call_depth(100);
+        assert_eq!(
+            Ok(Value::Int(99)),
+            interpreter.call("depth", &[Value::Int(99)])
+        );
+        let err = interpreter.call("depth", &[Value::Int(100)]).unwrap_err();
+        assert_eq!(ErrorCode::StackOverflow, err.code());
+        assert_eq!(
+            Ok(Value::Int(10)),
+            interpreter.call("depth", &[Value::Int(10)])
+        );
+    }
+
+    #[test]
+    fn interp_call_frames_released() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .eval("{ fn inverse(int n)->int { int local = 1; return local / n; } }")
+            .unwrap();
+        assert_eq!(Ok(Value::Int(1)), interpreter.call("inverse", &[Value::Int(1)]));
+        assert_eq!(
+            ErrorCode::DivisionByZero,
+            interpreter.eval("{ inverse(0) }").unwrap_err().code()
+        );
+        // A frame left behind by the failed call would make these locals.
+        interpreter.eval("{ int after = 2; }").unwrap();
+        assert_eq!(Some(Value::Int(2)), interpreter.get_global("after"));
+        assert_eq!(None, interpreter.get_global("local"));
+        assert_eq!(
+            Value::Int(2),
+            interpreter
+                .eval("{ fn twice(int n)->int { int local = n; return local * 2; } twice(1) }")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn interp_compare_ints() {
+        let cases = [
+            ("1 < 2", true),
+            ("2 < 1", false),
+            ("2 > 1", true),
+            ("1 > 1", false),
+            ("1 <= 1", true),
+            ("2 <= 1", false),
+            ("1 >= 1", true),
+            ("1 >= 2", false),
+            ("3 == 3", true),
+            ("3 == 4", false),
+            ("3 != 4", true),
+            ("3 != 3", false),
+        ];
+        for (source, expected) in cases.iter() {
+            assert_eq!(
+                Value::Bool(*expected),
+                Interpreter::new().eval_statement(source).unwrap(),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn interp_compare_floats() {
+        let cases = [
+            ("1.5 < 2.5", true),
+            ("2.5 > 1.5", true),
+            ("1.5 <= 1.5", true),
+            ("1.5 >= 2.5", false),
+            ("0.5 == 0.5", true),
+            ("0.5 != 0.5", false),
+        ];
+        for (source, expected) in cases.iter() {
+            assert_eq!(
+                Value::Bool(*expected),
+                Interpreter::new().eval_statement(source).unwrap(),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn interp_compare_evaluates_operands() {
+        assert_eq!(
+            Value::Bool(true),
+            Interpreter::new().eval("{ int a = 3; int b = 1; a == b + 2 }")
+                .unwrap()
+        )
+    }
+
+    #[test]
+    fn interp_compare_type_error() {
+        let err = Interpreter::new().eval("{ bool b = true; b < false }")
+            .unwrap_err();
+        assert_eq!(ErrorCode::TypeMismatch, err.code());
+
+        let err = Interpreter::new().eval_statement("1 == true")
+            .unwrap_err();
+        assert_eq!(ErrorCode::TypeMismatch, err.code());
+    }
+
+    #[test]
+    fn interp_logic() {
+        let cases = [
+            ("true && true", true),
+            ("true && false", false),
+            ("false || true", true),
+            ("false || false", false),
+            ("!false", true),
+            ("!0", true),
+            ("!5", false),
+            ("1 < 2 && 3 < 4", true),
+            ("1 < 2 && 4 < 3 || 5 == 5", true),
+            ("!(1 < 2) || false", false),
+            ("1 + 1 == 2 && 2 * 2 == 4", true),
+        ];
+        for (source, expected) in cases.iter() {
+            assert_eq!(
+                Value::Bool(*expected),
+                Interpreter::new().eval_statement(source).unwrap(),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn interp_logic_short_circuit() {
+        // 1 / 0 is never evaluated, so it can't raise a division error.
+        assert_eq!(
+            Value::Bool(false),
+            Interpreter::new().eval_statement("false && 1 / 0 == 0")
+                .unwrap()
+        );
+        assert_eq!(
+            Value::Bool(true),
+            Interpreter::new().eval_statement("1 < 2 || 1 / 0 == 0")
+                .unwrap()
+        );
+        assert_eq!(
+            ErrorCode::DivisionByZero,
+            Interpreter::new().eval_statement("true && 1 / 0 == 0")
+                .unwrap_err()
+                .code()
+        );
+    }
+
+    #[test]
+    fn interp_bool_variables() {
+        assert_eq!(
+            Value::Bool(false),
+            Interpreter::new().eval(
+                "
+            {
+                bool small = 10 < 3;
+                bool same = small == false;
+                small == same
+            }"
+            )
+            .unwrap()
+        )
+    }
+
+    #[test]
+    fn interp_bool_conditions() {
+        assert_eq!(
+            Value::Int(10),
+            Interpreter::new().eval(
+                "
+            {
+                int i = 0;
+                bool go = true;
+                while (go) {
+                    i = i + 1;
+                    if (i >= 10) {
+                        go = false;
+                    }
+                }
+                i
+            }"
+            )
+            .unwrap()
+        )
+    }
+
+    #[test]
+    fn interp_float_condition() {
+        assert_eq!(
+            Value::Int(1),
+            Interpreter::new().eval("{ if (0.5) { return 1; } else { return 2; } }")
+                .unwrap()
+        );
+        assert_eq!(
+            Value::Int(2),
+            Interpreter::new().eval("{ if (0.0) { return 1; } else { return 2; } }")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn interp_float_declaration() {
+        assert_eq!(
+            Value::Float(2.5),
+            Interpreter::new().eval("{ float f = 1.25; f * 2 }")
+                .unwrap()
+        );
+        assert_eq!(
+            Value::Float(4.0),
+            Interpreter::new().eval("{ float f = 3; f += 1; f }")
+                .unwrap()
+        );
+        assert_eq!(
+            Value::Float(2.5),
+            Interpreter::new().eval("{ float f = 1.5; f++; f }")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn interp_mixed_arithmetic() {
+        assert_eq!(
+            Value::Float(3.5),
+            Interpreter::new().eval_statement("1 + 2.5").unwrap()
+        );
+        assert_eq!(
+            Value::Float(2.5),
+            Interpreter::new().eval_statement("5 / 2.0").unwrap()
+        );
+        assert_eq!(
+            Value::Int(2),
+            Interpreter::new().eval_statement("5 / 2").unwrap()
+        );
+        assert_eq!(
+            Value::Bool(true),
+            Interpreter::new().eval_statement("3 == 3.0").unwrap()
+        );
+        assert_eq!(
+            Value::Bool(true),
+            Interpreter::new().eval_statement("2.5 < 3").unwrap()
+        );
+        assert_eq!(
+            Value::Float(0.30000000000000004),
+            Interpreter::new().eval_statement("0.1 + 0.2").unwrap()
+        );
+    }
+
+    #[test]
+    fn interp_float_unary() {
+        assert_eq!(
+            Value::Float(-1.5),
+            Interpreter::new().eval_statement("-1.5").unwrap()
+        );
+        assert_eq!(
+            Value::Float(1.5),
+            Interpreter::new().eval_statement("+-(-1.5)").unwrap()
+        );
+        assert_eq!(
+            Value::Bool(true),
+            Interpreter::new().eval_statement("!0.0").unwrap()
+        );
+    }
+
+    #[test]
+    fn interp_casts() {
+        let eval = |source: &str| Interpreter::new().eval_statement(source).unwrap();
+        assert_eq!(Value::Int(2), eval("(int)2.9"));
+        assert_eq!(Value::Int(-2), eval("(int)-2.9"));
+        assert_eq!(Value::Int(3), eval("(int)2.5 + 1"));
+        assert_eq!(Value::Float(3.5), eval("(float)7 / 2"));
+        assert_eq!(Value::Int(1), eval("(int)true"));
+        assert_eq!(Value::Bool(false), eval("(bool)0"));
+        assert_eq!(Value::Int(i32::MAX), eval("(int)2147483647.9"));
+        for source in &["(int)3000000000.0", "(int)-3000000000.0", "(int)(0.0 / 0.0)"] {
+            let err = Interpreter::new().eval_statement(source).unwrap_err();
+            assert_eq!(ErrorCode::InvalidCast, err.code(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn interp_division_by_zero() {
+        for source in &["{ 7 / 0 }", "{ 7 % (3 - 3) }", "{ int a = 1; a /= 0; }"] {
+            let err = Interpreter::new().eval(source)
+                .unwrap_err();
+            assert_eq!(ErrorCode::DivisionByZero, err.code(), "{}", source);
+        }
+        let err = Interpreter::new().eval_statement("7 % (3 - 3)")
+            .unwrap_err();
+        assert_eq!(3, err.span().col);
+        assert_eq!(
+            Value::Float(f64::INFINITY),
+            Interpreter::new().eval_statement("1.0 / 0").unwrap()
+        );
+    }
+
+    #[test]
+    fn interp_overflow_policies() {
+        let eval = |policy: OverflowPolicy, source: &str| {
+            let mut interpreter = Interpreter::new();
+            interpreter.set_overflow_policy(policy);
+            interpreter.eval(source)
+        };
+        let cases = [
+            ("{ int a = 2147483647; a + 1 }", i32::MIN, i32::MAX),
+            ("{ int a = 0 - 2147483647; a - 2 }", i32::MAX, i32::MIN),
+            ("{ int a = 65536; a * a }", 0, i32::MAX),
+            ("{ int a = 0 - 2147483647 - 1; a / -1 }", i32::MIN, i32::MAX),
+            ("{ int a = 0 - 2147483647 - 1; -a }", i32::MIN, i32::MAX),
+            ("{ int a = 2147483647; a++; a }", i32::MIN, i32::MAX),
+            ("{ int a = 2147483647; a *= 2; a }", -2, i32::MAX),
+            ("{ int a = 0 - 2147483647 - 1; abs(a) }", i32::MIN, i32::MAX),
+        ];
+        for (source, wrapped, saturated) in &cases {
+            let err = eval(OverflowPolicy::Trap, source).unwrap_err();
+            assert_eq!(ErrorCode::IntegerOverflow, err.code(), "{}", source);
+            assert_eq!(
+                Value::Int(*wrapped),
+                eval(OverflowPolicy::Wrap, source).unwrap(),
+                "{}",
+                source
+            );
+            assert_eq!(
+                Value::Int(*saturated),
+                eval(OverflowPolicy::Saturate, source).unwrap(),
+                "{}",
+                source
+            );
+        }
+        assert_eq!(
+            Value::Int(0),
+            eval(OverflowPolicy::Trap, "{ int a = 0 - 2147483647 - 1; a % -1 }").unwrap()
+        );
+    }
+
+    #[test]
+    fn interp_float_argument() {
+        assert_eq!(
+            Value::Float(3.0),
+            Interpreter::new().eval("{ fn half(float x) -> float { x / 2 } half(6) }")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn interp_bool_argument() {
+        assert_eq!(
+            Value::Int(1),
+            Interpreter::new().eval(
+                "
+            {
+                fn pick(bool b)->int{
+                    if (b) {
+                        return 1;
+                    }
+                    return 2;
+                }
+                pick(2 > 1)
+            }"
+            )
+            .unwrap()
+        )
+    }
+
+    #[test]
+    fn interp_left_associativity() {
+        let cases = [
+            ("10 - 3 - 2", 5),
+            ("100 / 10 / 5", 2),
+            ("2 * 3 % 4", 2),
+            ("20 % 7 * 2", 12),
+            ("1 - 2 + 3", 2),
+            ("-2 * 3", -6),
+            ("-(2 + 3) * 2", -10),
+            ("2 * -3 - -4", -2),
+        ];
+        for (source, expected) in cases.iter() {
+            assert_eq!(
+                Value::Int(*expected),
+                Interpreter::new().eval_statement(source).unwrap(),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn interp_relational_before_equality() {
+        assert_eq!(
+            Value::Bool(true),
+            Interpreter::new().eval_statement("1 < 2 == 3 < 4")
+                .unwrap()
+        )
+    }
+
+    #[test]
+    fn interp_chained_assignment() {
+        assert_eq!(
+            Value::Int(6),
+            Interpreter::new().eval("{ int a; int b; a = b = 3; a + b }")
+                .unwrap()
+        )
+    }
+
+    #[test]
+    fn interp_compound_assign() {
+        assert_eq!(
+            Value::Int(3),
+            Interpreter::new().eval("{ int a = 10; a += 5; a -= 1; a *= 2; a /= 4; a %= 4; a }")
+                .unwrap()
+        );
+        assert_eq!(
+            Value::Int(17),
+            Interpreter::new().eval("{ int a = 2; int b = 3; int c = (a += b) + (b *= 4); c }")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn interp_increment_decrement() {
+        assert_eq!(
+            Value::Int(5),
+            Interpreter::new().eval("{ int a = 5; a++ }")
+                .unwrap()
+        );
+        assert_eq!(
+            Value::Int(6),
+            Interpreter::new().eval("{ int a = 5; ++a }")
+                .unwrap()
+        );
+        assert_eq!(
+            Value::Int(4),
+            Interpreter::new().eval("{ int a = 5; a--; a }")
+                .unwrap()
+        );
+        assert_eq!(
+            Value::Int(-4),
+            Interpreter::new().eval("{ int a = 5; -(--a) }")
+                .unwrap()
+        );
+        assert_eq!(
+            Value::Int(10),
+            Interpreter::new().eval("{ int sum = 0; for (int i = 0; i < 5; i++) { sum += i; } sum }")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn interp_increment_non_variable() {
+        for input in &["{ ++3; }", "{ int a = 1; (a + 1)++; }", "{ 2 += 1; }"] {
+            let err = Interpreter::new().eval(input)
+                .unwrap_err();
+            assert_eq!(ErrorCode::InvalidAssignment, err.code());
+        }
+    }
+
+    #[test]
+    fn interp_nested_block_return() {
+        assert_eq!(
+            Value::Int(1),
+            Interpreter::new().eval("{ int a = 1; { return a; } 2 }")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn interp_call_non_function() {
+        let err = Interpreter::new().eval("{ int a = 1; a() }")
+            .unwrap_err();
+        assert_eq!(ErrorCode::TypeMismatch, err.code());
+        assert_eq!(14, err.span().col);
+    }
+
+    #[test]
+    fn interp_assign_to_expression() {
+        let err = Interpreter::new().eval("{ int a = 1; int b = 2; int c = 3; a * b = c; }")
+            .unwrap_err();
+        assert_eq!(ErrorCode::InvalidAssignment, err.code());
+    }
+
+    #[test]
+    fn interp_while() {
+        assert_eq!(
+            Value::Int(55),
+            Interpreter::new().eval(
+                "
+            {
+                int n = 10;
+                int sum = 0;
+                while (n) {
+                    sum = sum + n;
+                    n = n - 1;
+                }
+                sum
+            }"
+            )
+            .unwrap()
+        )
+    }
+
+    #[test]
+    fn interp_while_many_iterations() {
+        assert_eq!(
+            Value::Int(0),
+            Interpreter::new().eval("{ int n = 100000; while (n) { n = n - 1; } n }")
+                .unwrap()
+        )
+    }
+
+    #[test]
+    fn interp_for() {
+        assert_eq!(
+            Value::Int(15),
+            Interpreter::new().eval(
+                "
+            {
+                int total = 0;
+                for (int i = 5; i; i = i - 1) {
+                    total = total + i;
+                }
+                total
+            }"
+            )
+            .unwrap()
+        )
+    }
+
+    #[test]
+    fn interp_loop_tail_without_semi() {
+        assert_eq!(
+            Value::Int(6),
+            Interpreter::new()
+                .eval("{ int i = 3; int s = 0; while (i) { s = s + i; i = i - 1 } s }")
+                .unwrap()
+        );
+        assert_eq!(
+            Value::Int(10),
+            Interpreter::new()
+                .eval("{ int s = 0; for (int i = 4; i; i = i - 1) { s = s + i } s }")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn interp_for_scope() {
+        let err = Interpreter::new().eval("{ for (int i = 1; i; i = i - 1) { int j = i; } i }")
+            .unwrap_err();
+        assert_eq!(ErrorCode::UndeclaredVariable, err.code());
+    }
+
+    #[test]
+    fn interp_break() {
+        assert_eq!(
+            Value::Int(5),
+            Interpreter::new().eval(
+                "
+            {
+                int n = 0;
+                while (1) {
+                    n = n + 1;
+                    if (n - 5) {
+                    } else {
+                        break;
+                    }
+                }
+                n
+            }"
+            )
+            .unwrap()
+        )
+    }
+
+    #[test]
+    fn interp_continue() {
+        assert_eq!(
+            Value::Int(25),
+            Interpreter::new().eval(
+                "
+            {
+                int sum = 0;
+                for (int i = 10; i; i = i - 1) {
+                    if (i % 2) {
+                    } else {
+                        continue;
+                    }
+                    sum = sum + i;
+                }
+                sum
+            }"
+            )
+            .unwrap()
+        )
+    }
+
+    #[test]
+    fn interp_return_types() {
+        assert_eq!(
+            Value::Float(2.0),
+            Interpreter::new().eval("{ fn f()->float { return 2; } f() }").unwrap()
+        );
+        assert_eq!(
+            Value::Int(1),
+            Interpreter::new()
+                .eval("{ int a = 1; fn f(int *p) { if (*p) { return; } *p = 2; } f(&a); a }")
+                .unwrap()
+        );
+        // abs may give an int or a float, so only running it tells.
+        assert_eq!(
+            ErrorCode::TypeMismatch,
+            Interpreter::new()
+                .eval("{ fn f()->int { return abs(1.5); } f() }")
+                .unwrap_err()
+                .code()
+        );
+    }
+
+    #[test]
+    fn interp_return_from_loop() {
+        assert_eq!(
+            Value::Int(21),
+            Interpreter::new().eval(
+                "
+            {
+                fn firstMultiple(int n)->int{
+                    int i = 1;
+                    while (1) {
+                        if ((i * n) % 7) {
+                        } else {
+                            return i * n;
+                        }
+                        i = i + 1;
+                    }
+                }
+                firstMultiple(3)
+            }"
+            )
+            .unwrap()
+        )
+    }
+
+    #[test]
+    fn interp_if() {
+        assert_eq! {
+            Value::Int(5),
+            Interpreter::new().eval(
+                "
+            {
+                int a = 6;
+                if(1){
+                    return 5;
+                }
+                return 3;
+            }",
+            )
+            .unwrap()
+        }
+    }
+    #[test]
+    fn interp_else() {
+        assert_eq! {
+            Value::Int(3),
+            Interpreter::new().eval(
+                "
+            {
+                int a = 6;
+                if(0){
+                    return 5;
+                }else{
+                    return 3;
+                }
+            }",
+            )
+            .unwrap()
+        }
+    }
+
+    #[test]
+    fn interp_globals_outlive_eval() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .eval("{ int count = 1; fn bump()->int{ count += 1 } }")
+            .unwrap();
+        assert_eq!(Some(Value::Int(1)), interpreter.get_global("count"));
+        assert_eq!(Value::Int(2), interpreter.eval("{ bump() }").unwrap());
+        assert_eq!(Some(Value::Int(2)), interpreter.get_global("count"));
+        assert_eq!(None, interpreter.get_global("missing"));
+    }
+
+    #[test]
+    fn interp_set_global() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_global("limit", Value::Int(5)).unwrap();
+        assert_eq!(Value::Int(10), interpreter.eval("{ limit * 2 }").unwrap());
+        interpreter.eval("{ float f; }").unwrap();
+        interpreter.set_global("f", Value::Int(2)).unwrap();
+        assert_eq!(Some(Value::Float(2.0)), interpreter.get_global("f"));
+        let err = interpreter
+            .set_global("limit", Value::Bool(true))
+            .unwrap_err();
+        assert_eq!(ErrorCode::TypeMismatch, err.code());
+    }
+
+    #[test]
+    fn interp_call_from_host() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .eval("{ fn fact(int n)->int{ if(n <= 1){ return 1 } return n * fact(n - 1) } }")
+            .unwrap();
+        assert_eq!(
+            Ok(Value::Int(120)),
+            interpreter.call("fact", &[Value::Int(5)])
+        );
+        let err = interpreter.call("fact", &[Value::Float(1.5)]).unwrap_err();
+        assert_eq!(ErrorCode::TypeMismatch, err.code());
+        let err = interpreter.call("missing", &[]).unwrap_err();
+        assert_eq!(ErrorCode::UndeclaredVariable, err.code());
+    }
+
+    #[test]
+    fn interp_run_parsed_program() {
+        let program = Program::parse("{ int a = 2; a * 3 }").unwrap();
+        assert_eq!(Value::Int(6), Interpreter::new().run(&program).unwrap());
+        assert_eq!(Value::Int(6), Interpreter::new().run(&program).unwrap());
+
+        let source = "{ int runs; fn count()->int { runs += 1; return runs; } runs = 0; count() }";
+        let program = Program::parse(source).unwrap();
+        for engine in [Engine::Tree, Engine::Vm].iter() {
+            let mut interpreter = Interpreter::new();
+            interpreter.set_engine(*engine);
+            assert_eq!(Value::Int(1), interpreter.run(&program).unwrap());
+            interpreter.call("count", &[]).unwrap();
+            assert_eq!(Value::Int(1), interpreter.run(&program).unwrap());
+            assert_eq!(Some(Value::Int(1)), interpreter.get_global("runs"));
+            assert_eq!(Value::Int(1), interpreter.eval(source).unwrap());
+            // Another program declaring the same names still clashes.
+            let err = interpreter.eval("{ int runs; }").unwrap_err();
+            assert_eq!(ErrorCode::Redeclaration, err.code());
+        }
+    }
+
+    #[test]
+    fn interp_run_unchecked_program() {
+        let program = Program::parse("{ int a = 1; a = a + missing; b }").unwrap();
+        let mut interpreter = Interpreter::new();
+        assert_eq!(2, interpreter.check(&program).unwrap_err().len());
+        for engine in [Engine::Tree, Engine::Vm].iter() {
+            let mut interpreter = Interpreter::new();
+            interpreter.set_engine(*engine);
+            let err = interpreter.run(&program).unwrap_err();
+            assert_eq!(ErrorCode::UndeclaredVariable, err.code());
+            assert!(matches!(err, Error::Type { .. }));
+        }
+        let err = interpreter
+            .eval("{ int a = 1; a = a + missing; b }")
+            .unwrap_err();
+        assert_eq!(ErrorCode::UndeclaredVariable, err.code());
+    }
+
+    #[test]
+    fn interp_native_fn() {
+        let mut interpreter = Interpreter::new();
+        interpreter.register_fn("half", &[Type::FLOAT], Type::FLOAT, |args| match args {
+            [Value::Float(n)] => Ok(Value::Float(n / 2.0)),
+            _ => Err("expected a float".into()),
+        });
+        assert_eq!(Value::Float(2.5), interpreter.eval("{ half(5) }").unwrap());
+        assert_eq!(
+            Ok(Value::Float(0.75)),
+            interpreter.call("half", &[Value::Float(1.5)])
+        );
+        assert_eq!(
+            Value::Float(1.0),
+            interpreter
+                .eval("{ fn quarter(float a)->float{ half(half(a)) } quarter(4) }")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn interp_native_fn_checks() {
+        let mut interpreter = Interpreter::new();
+        interpreter.register_fn("id", &[Type::INT], Type::INT, |args| Ok(args[0].clone()));
+        interpreter.register_fn("broken", &[], Type::INT, |_| Ok(Value::Bool(true)));
+        interpreter.register_fn("fails", &[], Type::NONE, |_| Err("no disk".into()));
+
+        let source = "{ id(true) }";
+        let err = interpreter.eval(source).unwrap_err();
+        assert_eq!(
+            "<input>:1:6: [E0300] id argument 1 is of incorrect type: Should be INT, is BOOL\n{ id(true) }\n     ^^^^",
+            err.render(source, "<input>")
+        );
+        let err = interpreter.call("id", &[]).unwrap_err();
+        assert_eq!(ErrorCode::ArityMismatch, err.code());
+        assert_eq!("id takes 1 arguments, 0 given", err.message());
+        let err = interpreter.eval("{ broken() }").unwrap_err();
+        assert_eq!(ErrorCode::TypeMismatch, err.code());
+        let err = interpreter.eval("{ fails() }").unwrap_err();
+        assert_eq!(ErrorCode::NativeFailure, err.code());
+        assert_eq!("fails: no disk", err.message());
+    }
+
+    #[test]
+    fn interp_prelude_math() {
+        let mut interpreter = Interpreter::new();
+        let cases = [
+            ("{ abs(0 - 4) }", Value::Int(4)),
+            ("{ abs(-2.5) }", Value::Float(2.5)),
+            ("{ sqrt(16) }", Value::Float(4.0)),
+            ("{ floor(2.7) }", Value::Float(2.0)),
+            ("{ round(2.5) }", Value::Int(3)),
+            ("{ ceil(2.1) }", Value::Int(3)),
+            ("{ trunc(-2.7) }", Value::Int(-2)),
+            ("{ println(1.0) }", Value::Void),
+        ];
+        for (source, expected) in cases.iter() {
+            assert_eq!(*expected, interpreter.eval(source).unwrap(), "{}", source);
+        }
+        let mut call = |name: &str, args: &[Value]| interpreter.call(name, args);
+        assert_eq!(Ok(Value::Int(2)), call("min", &[Value::Int(2), Value::Int(3)]));
+        assert_eq!(Ok(Value::Float(3.0)), call("max", &[Value::Int(2), Value::Float(3.0)]));
+        assert_eq!(Ok(Value::Float(8.0)), call("pow", &[Value::Int(2), Value::Int(3)]));
+        let err = call("max", &[Value::Int(2), Value::Bool(true)]).unwrap_err();
+        assert_eq!("max: expected a number, found BOOL", err.message());
+    }
+
+    #[test]
+    fn interp_prelude_errors() {
+        let mut interpreter = Interpreter::new();
+        let err = interpreter.eval("{ sqrt(0 - 1) }").unwrap_err();
+        assert_eq!(ErrorCode::NativeFailure, err.code());
+        let err = interpreter.eval("{ round(3000000000.0) }").unwrap_err();
+        assert_eq!("round: 3000000000.0 does not fit in an int", err.message());
+        let err = interpreter.eval("{ abs(true) }").unwrap_err();
+        assert_eq!("abs: expected a number, found BOOL", err.message());
+    }
+
+    #[test]
+    fn interp_without_prelude() {
+        let err = Interpreter::without_prelude()
+            .eval("{ print(1) }")
+            .unwrap_err();
+        assert_eq!(ErrorCode::UndeclaredVariable, err.code());
+    }
+
+    #[test]
+    fn interp_display_values() {
+        assert_eq!("3", Value::Int(3).to_string());
+        assert_eq!("2.0", Value::Float(2.0).to_string());
+        assert_eq!("false", Value::Bool(false).to_string());
+        assert_eq!("void", Value::Void.to_string());
+        let mut interpreter = Interpreter::new();
+        assert_eq!("fn sqrt", interpreter.eval("{ sqrt }").unwrap().to_string());
+    }
+
+    #[test]
+    fn interp_strings() {
+        let cases = [
+            (r#"{ string s = "ab"; s + "c" }"#, Value::Str("abc".into())),
+            (r#"{ "abc"[1] }"#, Value::Char('b')),
+            (r#"{ string s = "ab"; s += "cd"; s[3] }"#, Value::Char('d')),
+            (r#"{ "abc" < "abd" }"#, Value::Bool(true)),
+            (r#"{ "b" >= "abc" }"#, Value::Bool(true)),
+            (r#"{ "a\tb" == "a	b" }"#, Value::Bool(true)),
+            (r#"{ len("héllo") }"#, Value::Int(5)),
+            (r#"{ to_string(2.0) + "!" }"#, Value::Str("2.0!".into())),
+            (r#"{ parse_int(" 42 ") + 1 }"#, Value::Int(43)),
+            (r#"{ parse_float("2.5") }"#, Value::Float(2.5)),
+        ];
+        for (source, expected) in cases.iter() {
+            assert_eq!(*expected, Interpreter::new().eval(source).unwrap(), "{}", source);
+        }
+        let mut interpreter = Interpreter::new();
+        assert_eq!(
+            Ok(Value::Str("ell".into())),
+            interpreter.call(
+                "substr",
+                &[Value::Str("hello".into()), Value::Int(1), Value::Int(3)]
+            )
+        );
+        let err = interpreter
+            .call(
+                "substr",
+                &[Value::Str("hello".into()), Value::Int(4), Value::Int(3)],
+            )
+            .unwrap_err();
+        assert_eq!("substr: 4..7 is out of bounds for length 5", err.message());
+    }
+
+    #[test]
+    fn interp_string_errors() {
+        let source = r#"{ string s = "abc"; s[3] }"#;
+        let err = Interpreter::new().eval(source).unwrap_err();
+        assert_eq!(
+            "<input>:1:23: [E0408] Index 3 out of bounds for length 3\n{ string s = \"abc\"; s[3] }\n                      ^",
+            err.render(source, "<input>")
+        );
+        let err = Interpreter::new().eval(r#"{ "a" + 1 }"#).unwrap_err();
+        assert_eq!(ErrorCode::TypeMismatch, err.code());
+        let err = Interpreter::new().eval(r#"{ "a" - "b" }"#).unwrap_err();
+        assert_eq!(ErrorCode::TypeMismatch, err.code());
+        let err = Interpreter::new().eval(r#"{ "a" < 1 }"#).unwrap_err();
+        assert_eq!(ErrorCode::TypeMismatch, err.code());
+        let err = Interpreter::new().eval("{ int n = 12; n[0] }").unwrap_err();
+        assert_eq!(ErrorCode::TypeMismatch, err.code());
+    }
+
+    #[test]
+    fn interp_condition_type_error() {
+        let err = Interpreter::new()
+            .eval(r#"{ if ("yes") { return 1; } }"#)
+            .unwrap_err();
+        assert_eq!(ErrorCode::TypeMismatch, err.code());
+        assert_eq!(7, err.span().col);
+    }
+
+    #[test]
+    fn interp_chars() {
+        let cases = [
+            ("{ 'a' + 1 }", Value::Int(98)),
+            ("{ 'z' - 'a' }", Value::Int(25)),
+            ("{ (char)('a' + 1) }", Value::Char('b')),
+            ("{ (int)'A' }", Value::Int(65)),
+            ("{ 'a' + 1 == 'b' }", Value::Bool(true)),
+            ("{ 'a' < 'b' }", Value::Bool(true)),
+            ("{ 'a' >= 98.5 }", Value::Bool(false)),
+            ("{ char c = 'x'; c++; c }", Value::Char('y')),
+            ("{ char c = 'x'; c += 2; c }", Value::Char('z')),
+            ("{ char c = 'x'; c-- }", Value::Char('x')),
+            ("{ if ('\\0') { return 1; } 2 }", Value::Int(2)),
+            (r#"{ "ab" + 'c' }"#, Value::Str("abc".into())),
+            (r#"{ "abc"[2] == 'c' }"#, Value::Bool(true)),
+            ("{ to_string('q') }", Value::Str("q".into())),
+        ];
+        for (source, expected) in cases.iter() {
+            assert_eq!(*expected, Interpreter::new().eval(source).unwrap(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn interp_char_errors() {
+        let err = Interpreter::new().eval("{ (char)(0 - 1) }").unwrap_err();
+        assert_eq!(ErrorCode::InvalidCast, err.code());
+        assert_eq!("-1 is not a char code", err.message());
+        let err = Interpreter::new().eval(r#"{ 'a' == "a" }"#).unwrap_err();
+        assert_eq!(ErrorCode::TypeMismatch, err.code());
+        let err = Interpreter::new().eval(r#"{ 'a' + "b" }"#).unwrap_err();
+        assert_eq!(ErrorCode::TypeMismatch, err.code());
+    }
+
+    #[test]
+    fn interp_arrays() {
+        let cases = [
+            ("{ int a[3]; a[1] }", Value::Int(0)),
+            ("{ int a[3] = {1, 2}; a[0] + a[1] + a[2] }", Value::Int(3)),
+            ("{ float a[] = {1, 2.5}; a[0] }", Value::Float(1.0)),
+            ("{ int a[3]; a[2] = 7; a[2] }", Value::Int(7)),
+            ("{ int a[2] = {1, 2}; a[0] += 5; a[1]++; a[0] * a[1] }", Value::Int(18)),
+            (
+                "{ char s[2]; s[0] = 'h'; s[1] = 105; to_string(s[0]) + s[1] }",
+                Value::Str("hi".into()),
+            ),
+            ("{ int a[] = {4, 5, 6}; len(a) }", Value::Int(3)),
+            (
+                "{ int a[4]; for (int i = 0; i < 4; i++) { a[i] = i * i; } a[3] }",
+                Value::Int(9),
+            ),
+            (
+                "{ fn fill(int a[]) { a[0] = 42; } int b[1]; fill(b); b[0] }",
+                Value::Int(42),
+            ),
+            ("{ int a[] = {1, 2}; to_string(a) }", Value::Str("{1, 2}".into())),
+        ];
+        for (source, expected) in cases.iter() {
+            assert_eq!(*expected, Interpreter::new().eval(source).unwrap(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn interp_array_errors() {
+        let source = "{ int a[3]; a[3] = 1; }";
+        let err = Interpreter::new().eval(source).unwrap_err();
+        assert_eq!(
+            "<input>:1:15: [E0408] Index 3 out of bounds for length 3\n{ int a[3]; a[3] = 1; }\n              ^",
+            err.render(source, "<input>")
+        );
+        let cases = [
+            ("{ int a[3]; a[0 - 1] }", ErrorCode::IndexOutOfBounds),
+            ("{ int a[3]; a[true] }", ErrorCode::TypeMismatch),
+            ("{ int a[3]; a[0] = 1.5; }", ErrorCode::TypeMismatch),
+            ("{ int a[2] = {1, true}; }", ErrorCode::TypeMismatch),
+            ("{ int a[2]; int b[2]; a = b; }", ErrorCode::InvalidAssignment),
+            ("{ fn f(int a[]) { } f(1); }", ErrorCode::TypeMismatch),
+            ("{ int a[2000000000]; }", ErrorCode::Unsupported),
+        ];
+        for (source, code) in cases.iter() {
+            let err = Interpreter::new().eval(source).unwrap_err();
+            assert_eq!(*code, err.code(), "{}", source);
+        }
+        let err = Interpreter::new().eval("{ int a[4194305]; }").unwrap_err();
+        assert_eq!(7, err.span().col);
+    }
+
+    #[test]
+    fn interp_structs() {
+        let point = "struct Point { int x; int y; }";
+        let cases = [
+            ("struct Point p; p.x", Value::Int(0)),
+            ("struct Point p = {1, 2}; p.x + p.y", Value::Int(3)),
+            ("struct Point p; p.y = 4; p.y += 1; p.y++; p.y", Value::Int(6)),
+            ("struct Point p = {1, 2}; struct Point q = p; q.x = 9; p.x", Value::Int(1)),
+            ("struct Point p = {1, 2}; to_string(p)", Value::Str("Point { x: 1, y: 2 }".into())),
+            (
+                "fn swap(struct Point p) -> struct Point { int t = p.x; p.x = p.y; p.y = t; \
+                 return p; } struct Point p = {1, 2}; swap(p).x * 10 + p.x",
+                Value::Int(21),
+            ),
+            (
+                "struct Line { struct Point a; struct Point b; } struct Line l; \
+                 l.b.y = 3; l.b.y + l.a.y",
+                Value::Int(3),
+            ),
+            ("struct Point ps[2]; ps[1].x = 5; ps[1].x + ps[0].x", Value::Int(5)),
+            (
+                "struct F { float f; string s; } struct F v = {1}; v.s = \"a\"; to_string(v)",
+                Value::Str("F { f: 1.0, s: a }".into()),
+            ),
+        ];
+        for (source, expected) in cases.iter() {
+            let source = format!("{{ {} {} }}", point, source);
+            assert_eq!(*expected, Interpreter::new().eval(&source).unwrap(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn interp_struct_errors() {
+        let source = "{ struct P { int x; } struct P p; p.z = 1; }";
+        let err = Interpreter::new().eval(source).unwrap_err();
+        assert_eq!(
+            format!(
+                "<input>:1:37: [E0302] struct P has no field z\n{}\n{}^",
+                source,
+                " ".repeat(36)
+            ),
+            err.render(source, "<input>")
+        );
+        let cases = [
+            ("{ struct Q q; }", ErrorCode::UnknownType),
+            ("{ struct P { struct P p; } }", ErrorCode::UnknownType),
+            ("{ struct P { int x; } struct P { int y; } }", ErrorCode::Redeclaration),
+            ("{ struct P { int x; } struct P p; p.x = true; }", ErrorCode::TypeMismatch),
+            ("{ struct P { int x; } struct P p = {1, 2}; }", ErrorCode::TypeMismatch),
+            ("{ struct P { int x; } struct P p; p = 1; }", ErrorCode::TypeMismatch),
+            ("{ struct P { int x; } struct P p = 1; }", ErrorCode::TypeMismatch),
+            ("{ int a = 1; a.x }", ErrorCode::TypeMismatch),
+            ("{ struct P { int x; } struct P p; &p.x; }", ErrorCode::Unsupported),
+            (
+                "{ struct P { int x; } struct P p; struct P *s = &p; &s->x; }",
+                ErrorCode::Unsupported,
+            ),
+            (
+                "{ struct P { int x; } fn f(struct P p) -> int { return p.x; } f(1); }",
+                ErrorCode::TypeMismatch,
+            ),
+        ];
+        for (source, code) in cases.iter() {
+            let err = Interpreter::new().eval(source).unwrap_err();
+            assert_eq!(*code, err.code(), "{}", source);
+        }
+        // Fields have no address of their own, with or without the checker.
+        let program = Program::parse("{ struct P { int x; } struct P p; p.x = 1; &p.x }").unwrap();
+        for engine in [Engine::Tree, Engine::Vm].iter() {
+            let mut interpreter = Interpreter::new();
+            interpreter.set_engine(*engine);
+            let err = interpreter.run(&program).unwrap_err();
+            assert_eq!(ErrorCode::Unsupported, err.code());
+        }
+    }
+
+    #[test]
+    fn interp_pointers() {
+        let cases = [
+            ("{ int x = 1; int *p = &x; *p = 5; x }", Value::Int(5)),
+            ("{ int x = 1; int *p = &x; int **q = &p; **q += 2; x }", Value::Int(3)),
+            ("{ int a[] = {1, 2, 3}; int *p = a; *(p + 2) }", Value::Int(3)),
+            ("{ int a[] = {1, 2, 3}; int *p = &a[2]; p--; *p }", Value::Int(2)),
+            ("{ int a[] = {1, 2, 3}; int *p = a + 1; p[1] = 9; a[2] }", Value::Int(9)),
+            ("{ int a[4]; int *p = &a[3]; p - a }", Value::Int(3)),
+            ("{ int a[4]; a + 1 == &a[1] }", Value::Bool(true)),
+            ("{ int a[4]; &a[3] > &a[1] }", Value::Bool(true)),
+            ("{ int *p = NULL; p == NULL }", Value::Bool(true)),
+            ("{ int x; int *p = &x; if (p) { 1 } else { 2 } }", Value::Int(1)),
+            ("{ fn inc(int *p) { *p += 1; } int x = 1; inc(&x); inc(&x); x }", Value::Int(3)),
+            (
+                "{ struct P { int x; } struct P v; struct P *p = &v; p->x = 4; (*p).x + v.x }",
+                Value::Int(8),
+            ),
+            (
+                "{ struct N { int v; struct N *next; } struct N b = {2}; \
+                 struct N a = {1, &b}; a.next->v }",
+                Value::Int(2),
+            ),
+            (
+                "{ int s = 0; int a[] = {1, 2, 3}; \
+                 for (int *p = a; p < a + 3; p++) { s += *p; } s }",
+                Value::Int(6),
+            ),
+            ("{ int *p = NULL; to_string(p) }", Value::Str("NULL".into())),
+        ];
+        for (source, expected) in cases.iter() {
+            assert_eq!(*expected, Interpreter::new().eval(source).unwrap(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn interp_pointer_errors() {
+        let source = "{ int *p = NULL; *p = 1; }";
+        let err = Interpreter::new().eval(source).unwrap_err();
+        assert_eq!(
+            "<input>:1:19: [E0410] Null pointer dereference\n{ int *p = NULL; *p = 1; }\n                  ^",
+            err.render(source, "<input>")
+        );
+        let cases = [
+            ("{ int *p; { int x = 1; p = &x; } *p }", ErrorCode::DanglingPointer),
+            (
+                "{ fn f() -> int* { int x; return &x; } int *p = f(); *p }",
+                ErrorCode::DanglingPointer,
+            ),
+            ("{ int a[2]; int *p = a + 2; *p }", ErrorCode::IndexOutOfBounds),
+            ("{ int a[2]; int *p = a; p[0 - 1] = 1; }", ErrorCode::IndexOutOfBounds),
+            ("{ int x; int *p = &x; *p }", ErrorCode::UninitializedVariable),
+            ("{ int *p = NULL; p + 1 }", ErrorCode::NullPointer),
+            ("{ int x = 1; int *p = &x; *p = 1.5; }", ErrorCode::TypeMismatch),
+            ("{ int x = 1; float *p = &x; }", ErrorCode::TypeMismatch),
+            ("{ int *p = 1; }", ErrorCode::TypeMismatch),
+            ("{ int a[2]; int b[2]; &a[0] < &b[0] }", ErrorCode::TypeMismatch),
+            ("{ int a[2]; int b[2]; &a[0] - &b[0] }", ErrorCode::TypeMismatch),
+            ("{ int x = 1; *x }", ErrorCode::TypeMismatch),
+            ("{ struct P { int x; } struct P v; &v.x }", ErrorCode::Unsupported),
+        ];
+        for (source, code) in cases.iter() {
+            let err = Interpreter::new().eval(source).unwrap_err();
+            assert_eq!(*code, err.code(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn interp_heap() {
+        let cases = [
+            ("{ int *p = malloc(3); p[2] = 7; int n = p[2]; free(p); n }", Value::Int(7)),
+            ("{ float *p = calloc(4, 1); float f = p[3]; free(p); f }", Value::Float(0.0)),
+            ("{ int *p = calloc(2, 2); p[1] += 5; int n = p[1] + p[3]; free(p); n }", Value::Int(5)),
+            ("{ free(NULL); 1 }", Value::Int(1)),
+            (
+                "{ int n = 0; for (int i = 0; i < 3; i++) { int *p = malloc(3000000); free(p); n++; } n }",
+                Value::Int(3),
+            ),
+            (
+                "{ struct N { int v; struct N *next; } struct N *a = malloc(1); \
+                 a->v = 1; a->next = malloc(1); a->next->v = 2; a->next->next = NULL; \
+                 int s = 0; for (struct N *n = a; n != NULL; n = n->next) { s += n->v; } \
+                 free(a->next); free(a); s }",
+                Value::Int(3),
+            ),
+        ];
+        for (source, expected) in cases.iter() {
+            let mut interpreter = Interpreter::new();
+            assert_eq!(*expected, interpreter.eval(source).unwrap(), "{}", source);
+            assert_eq!(None, interpreter.leak_report("<input>"), "{}", source);
+        }
+    }
+
+    #[test]
+    fn interp_heap_errors() {
+        let cases = [
+            ("{ int *p = malloc(1); free(p); *p = 1; }", ErrorCode::UseAfterFree),
+            ("{ int *p = malloc(1); free(p); free(p); }", ErrorCode::DoubleFree),
+            ("{ int *p = malloc(2); free(p + 1); }", ErrorCode::InvalidFree),
+            ("{ int x; free(&x); }", ErrorCode::InvalidFree),
+            ("{ int *p = malloc(2); p[2] = 1; }", ErrorCode::IndexOutOfBounds),
+            ("{ int *p = malloc(2); p[0] }", ErrorCode::UninitializedVariable),
+            ("{ int *p = malloc(0 - 1); }", ErrorCode::NativeFailure),
+            ("{ int *p = malloc(2000000000); }", ErrorCode::NativeFailure),
+            ("{ int *p = calloc(65536, 65536); }", ErrorCode::NativeFailure),
+            ("{ while (1) { malloc(3000000); } }", ErrorCode::NativeFailure),
+            ("{ int *p = malloc(3000000); int *q = calloc(1000000, 2); }", ErrorCode::NativeFailure),
+            ("{ free(1); }", ErrorCode::TypeMismatch),
+        ];
+        for (source, code) in cases.iter() {
+            let err = Interpreter::new().eval(source).unwrap_err();
+            assert_eq!(*code, err.code(), "{}", source);
+        }
+        let err = Interpreter::new().eval("{ int *p;\n p = malloc(1000000000); }").unwrap_err();
+        assert_eq!((2, 6), (err.span().line, err.span().col));
+    }
+
+    #[test]
+    fn interp_leak_report() {
+        let mut interpreter = Interpreter::new();
+        assert_eq!(None, interpreter.leak_report("leaks.c"));
+        let source = "{\n int *a = malloc(4);\n int *b = calloc(2, 1);\n int *c = malloc(1);\n \
+                      free(b);\n}";
+        interpreter.eval(source).unwrap();
+        let spans: Vec<(usize, usize, usize)> = interpreter
+            .leaks()
+            .iter()
+            .map(|leak| (leak.span.line, leak.span.col, leak.cells))
+            .collect();
+        assert_eq!(vec![(2, 11, 4), (4, 11, 1)], spans);
+        assert_eq!(
+            Some(
+                "leaks.c:2:11: 4 cells allocated here never freed\n\
+                 leaks.c:4:11: 1 cell allocated here never freed\n\
+                 leaked 5 cells in 2 allocations"
+                    .to_string()
+            ),
+            interpreter.leak_report("leaks.c")
+        );
+    }
+}
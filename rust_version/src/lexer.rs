@@ -0,0 +1,839 @@
+use crate::error::{Error, ErrorCode, Span};
+use std::collections::HashMap;
+use std::iter::FromIterator;
+
+/*
+ * TODO: Implement simple namespace
+ *      Make types hold values? No... Use relevant token, like digit
+ *      figure out type system...
+ *      14/2/2020: Still haven't figured it out
+ */
+
+// enum Category{
+//     FUNCTION(Type, Box<ASTreeNode>),
+//     VAR(Type)
+// }
+
+/// The types a value or declaration can have.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Type {
+    /// 32 bit signed integer.
+    INT,
+    /// 64 bit float.
+    FLOAT,
+    /// Text.
+    STRING,
+    /// A single unicode char.
+    CHAR,
+    /// A function.
+    FUNC,
+    /// No value at all: what statements and `void` functions evaluate to.
+    /// A `void *` points to it.
+    NONE,
+    /// Reserved for types as values.
+    _TYPE,
+    /// `true` or `false`.
+    BOOL,
+    /// An array of the given element type, of any length.
+    ARRAY(Box<Type>),
+    /// A struct, by the name it was declared with.
+    STRUCT(String),
+    /// A pointer to a value of the given type. `NULL` points to `NONE`.
+    POINTER(Box<Type>),
+    /// Any value. Only native functions declare it, for parameters and
+    /// results they check themselves.
+    ANY,
+}
+
+#[derive(Clone, Debug, PartialEq, Copy)]
+pub(crate) enum Compare {
+    LT,
+    GT,
+    EQ,
+    NE,
+    LE,
+    GE,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Token {
+    FLOAT(f64),
+    DIGIT(i32),
+    STRING(String),
+    CHAR(char),
+    ADDOP(AddOp),
+    MULOP(MulOp),
+    UNOP(UnaryOp),
+    LPAREN,
+    RPAREN,
+    LBRACE,
+    RBRACE,
+    LBRACKET,
+    RBRACKET,
+    SEMI,
+    COMPARE(Compare),
+    LOGIC(Logic),
+    ASSIGN,
+    ADDASSIGN(AddOp),
+    MULASSIGN(MulOp),
+    INCR,
+    DECR,
+    EOF,
+    COMMA,
+    IDENT(String),
+    RET,
+    ARROW,
+    Type(Type),
+    If,
+    Else,
+    While,
+    For,
+    Break,
+    Continue,
+    Struct,
+    DOT,
+    AMP,
+    NULL,
+    BOOL(Bool),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum AddOp {
+    PLUS,
+    MINUS,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum UnaryOp {
+    PLUS,
+    MINUS,
+    NOT,
+    PREINC,
+    PREDEC,
+    POSTINC,
+    POSTDEC,
+    DEREF,
+    ADDR,
+}
+
+#[derive(Clone, Debug, PartialEq, Copy)]
+pub(crate) enum Logic {
+    AND,
+    OR,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum MulOp {
+    MULT,
+    DIV,
+    MODU,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Bool {
+    True,
+    False,
+}
+
+impl From<bool> for Bool {
+    fn from(b: bool) -> Bool {
+        if b {
+            Bool::True
+        } else {
+            Bool::False
+        }
+    }
+}
+
+pub(crate) struct Lexer {
+    pub(crate) input: Vec<char>,
+    pub(crate) position: usize,
+    pub(crate) current_token: Token,
+    pub(crate) current_span: Span,
+    pub(crate) len: usize,
+    pub(crate) restricted_words: HashMap<String, Token>,
+    // Location bookkeeping, valid for every char before `tracked`.
+    pub(crate) tracked: usize,
+    pub(crate) byte: usize,
+    pub(crate) line: usize,
+    pub(crate) col: usize,
+}
+
+impl Lexer {
+    pub(crate) fn digit(&mut self) -> Result<Token, Error> {
+        let mut number_so_far = String::new();
+
+        while self.position < self.len && self.input[self.position].is_ascii_digit() {
+            number_so_far.push(self.input[self.position]);
+            self.position += 1;
+        }
+
+        if self.position < self.len && self.input[self.position] == '.' {
+            number_so_far.push(self.input[self.position]);
+            self.position += 1;
+            while self.position < self.len && self.input[self.position].is_ascii_digit() {
+                number_so_far.push(self.input[self.position]);
+                self.position += 1;
+            }
+            Ok(Token::FLOAT(number_so_far.parse().unwrap()))
+        } else {
+            match number_so_far.parse() {
+                Ok(n) => Ok(Token::DIGIT(n)),
+                Err(_) => Err(Error::new(
+                    ErrorCode::InvalidNumber,
+                    Span::default(),
+                    format!("Integer literal {} is out of range", number_so_far),
+                )),
+            }
+        }
+    }
+
+    pub(crate) fn identifier(&mut self) -> Token {
+        let mut string_so_far = String::new();
+        // Like C, identifiers may contain underscores.
+        while self.position < self.len
+            && (self.input[self.position].is_alphanumeric() || self.input[self.position] == '_')
+        {
+            string_so_far.push(self.input[self.position]);
+            self.position += 1;
+        }
+        if let Some(i) = self.restricted_words.get(&string_so_far) {
+            // MUCH cleaner than a match.
+            return i.clone();
+        }
+        Token::IDENT(string_so_far)
+    }
+
+    /**
+     * A double quoted string literal, entered after the opening quote. A
+     * string ends on the line it starts on.
+     */
+    pub(crate) fn string(&mut self) -> Result<Token, Error> {
+        let mut string_so_far = String::new();
+        loop {
+            match self.peek() {
+                None | Some('\n') => {
+                    return Err(Error::new(
+                        ErrorCode::UnterminatedString,
+                        Span::default(),
+                        "Unterminated string literal",
+                    ))
+                }
+                Some('"') => {
+                    self.position += 1;
+                    return Ok(Token::STRING(string_so_far));
+                }
+                Some('\\') => {
+                    self.position += 1;
+                    string_so_far.push(self.escape()?);
+                }
+                Some(c) => {
+                    self.position += 1;
+                    string_so_far.push(c);
+                }
+            }
+        }
+    }
+
+    /// A single quoted char literal, entered after the opening quote.
+    pub(crate) fn char_literal(&mut self) -> Result<Token, Error> {
+        let c = match self.peek() {
+            Some('\\') => {
+                self.position += 1;
+                self.escape()?
+            }
+            Some(c) if c != '\'' && c != '\n' => {
+                self.position += 1;
+                c
+            }
+            _ => return Err(invalid_char()),
+        };
+        if self.peek() != Some('\'') {
+            return Err(invalid_char());
+        }
+        self.position += 1;
+        Ok(Token::CHAR(c))
+    }
+
+    /// The char a C escape sequence stands for, entered after the backslash.
+    pub(crate) fn escape(&mut self) -> Result<char, Error> {
+        let c = match self.peek() {
+            Some(c) => c,
+            None => {
+                return Err(Error::new(
+                    ErrorCode::UnterminatedString,
+                    Span::default(),
+                    "Unterminated string literal",
+                ))
+            }
+        };
+        self.position += 1;
+        match c {
+            'n' => Ok('\n'),
+            't' => Ok('\t'),
+            'r' => Ok('\r'),
+            '0' => Ok('\0'),
+            '\\' | '\'' | '"' => Ok(c),
+            _ => Err(Error::new(
+                ErrorCode::InvalidEscape,
+                Span::default(),
+                format!("Unknown escape sequence: \\{}", c),
+            )),
+        }
+    }
+
+    pub(crate) fn peek(&self) -> Option<char> {
+        if self.position >= self.len {
+            None
+        } else {
+            Some(self.input[self.position])
+        }
+    }
+
+    pub(crate) fn skip_comment(&mut self) {
+        while self.position < self.len && self.input[self.position] != '\n' {
+            self.position += 1;
+        }
+    }
+
+    pub(crate) fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() {
+                self.position += 1;
+            } else if c == '/' && self.input.get(self.position + 1) == Some(&'/') {
+                self.skip_comment();
+            } else {
+                break;
+            }
+        }
+    }
+
+    /**
+     * Walks the chars between the last tracked position and the current one,
+     * keeping the byte offset, line and column up to date.
+     */
+    pub(crate) fn track_location(&mut self) {
+        while self.tracked < self.position {
+            let c = self.input[self.tracked];
+            self.byte += c.len_utf8();
+            if c == '\n' {
+                self.line += 1;
+                self.col = 1;
+            } else {
+                self.col += 1;
+            }
+            self.tracked += 1;
+        }
+    }
+
+    pub(crate) fn get_next_token(&mut self) -> Result<(), Error> {
+        self.skip_whitespace();
+        self.track_location();
+        let start = self.position;
+
+        let token = self.scan_token();
+
+        let width: usize = self.input[start..self.position]
+            .iter()
+            .map(|c| c.len_utf8())
+            .sum();
+        self.current_span = Span {
+            start: self.byte,
+            end: self.byte + width,
+            line: self.line,
+            col: self.col,
+        };
+        match token {
+            Ok(token) => {
+                self.current_token = token;
+                Ok(())
+            }
+            Err(e) => Err(e.with_span(self.current_span)),
+        }
+    }
+
+    // Errors returned from here are located by `get_next_token`.
+    pub(crate) fn scan_token(&mut self) -> Result<Token, Error> {
+        let current_char = match self.peek() {
+            Some(c) => c,
+            None => return Ok(Token::EOF),
+        };
+
+        if current_char.is_ascii_digit() {
+            return self.digit();
+        }
+
+        if current_char.is_alphabetic() || current_char == '_' {
+            return Ok(self.identifier());
+        }
+        self.position += 1;
+
+        let token = match current_char {
+            '+' => match self.peek() {
+                Some('+') => {
+                    self.position += 1;
+                    Token::INCR
+                }
+                Some('=') => {
+                    self.position += 1;
+                    Token::ADDASSIGN(AddOp::PLUS)
+                }
+                _ => Token::ADDOP(AddOp::PLUS),
+            },
+            '-' => match self.peek() {
+                Some('>') => {
+                    self.position += 1;
+                    Token::ARROW
+                }
+                Some('-') => {
+                    self.position += 1;
+                    Token::DECR
+                }
+                Some('=') => {
+                    self.position += 1;
+                    Token::ADDASSIGN(AddOp::MINUS)
+                }
+                _ => Token::ADDOP(AddOp::MINUS),
+            },
+            '*' | '/' | '%' => {
+                let op = match current_char {
+                    '*' => MulOp::MULT,
+                    '/' => MulOp::DIV,
+                    _ => MulOp::MODU,
+                };
+                match self.peek() {
+                    Some('=') => {
+                        self.position += 1;
+                        Token::MULASSIGN(op)
+                    }
+                    _ => Token::MULOP(op),
+                }
+            }
+            '(' => Token::LPAREN,
+            ')' => Token::RPAREN,
+            '{' => Token::LBRACE,
+            '}' => Token::RBRACE,
+            '[' => Token::LBRACKET,
+            ']' => Token::RBRACKET,
+            '"' => return self.string(),
+            '\'' => return self.char_literal(),
+            ';' => Token::SEMI,
+            '=' => match self.peek() {
+                Some('=') => {
+                    self.position += 1;
+                    Token::COMPARE(Compare::EQ)
+                }
+                _ => Token::ASSIGN,
+            },
+            '<' => match self.peek() {
+                Some('=') => {
+                    self.position += 1;
+                    Token::COMPARE(Compare::LE)
+                }
+                _ => Token::COMPARE(Compare::LT),
+            },
+            '>' => match self.peek() {
+                Some('=') => {
+                    self.position += 1;
+                    Token::COMPARE(Compare::GE)
+                }
+                _ => Token::COMPARE(Compare::GT),
+            },
+            ',' => Token::COMMA,
+            '.' => Token::DOT,
+            '!' => match self.peek() {
+                Some('=') => {
+                    self.position += 1;
+                    Token::COMPARE(Compare::NE)
+                }
+                _ => Token::UNOP(UnaryOp::NOT),
+            },
+            '&' => match self.peek() {
+                Some('&') => {
+                    self.position += 1;
+                    Token::LOGIC(Logic::AND)
+                }
+                _ => Token::AMP,
+            },
+            '|' => match self.peek() {
+                Some('|') => {
+                    self.position += 1;
+                    Token::LOGIC(Logic::OR)
+                }
+                _ => return Err(unrecognized(current_char)),
+            },
+            _ => return Err(unrecognized(current_char)),
+        };
+        Ok(token)
+    }
+
+    pub(crate) fn new(input: &str) -> Result<Lexer, Error> {
+        if input.is_empty() {
+            return Err(Error::new(
+                ErrorCode::EmptyInput,
+                Span {
+                    line: 1,
+                    col: 1,
+                    ..Span::default()
+                },
+                "Must have length",
+            ));
+        }
+        // Only trim the end, so line and column numbers match the original text.
+        let input: Vec<char> = input.trim_end().chars().collect();
+        // let reserved_keys : HashSet<String> = vec!["int".into()].iter().cloned().collect();
+        let restricted_words: HashMap<String, Token> = HashMap::from_iter(vec![
+            ("int".into(), Token::Type(Type::INT)),
+            ("bool".into(), Token::Type(Type::BOOL)),
+            ("float".into(), Token::Type(Type::FLOAT)),
+            ("string".into(), Token::Type(Type::STRING)),
+            ("char".into(), Token::Type(Type::CHAR)),
+            ("void".into(), Token::Type(Type::NONE)),
+            ("true".into(), Token::BOOL(Bool::True)),
+            ("false".into(), Token::BOOL(Bool::False)),
+            ("test".into(), Token::EOF),
+            ("return".into(), Token::RET),
+            ("fn".into(), Token::Type(Type::FUNC)),
+            ("if".into(), Token::If),
+            ("else".into(), Token::Else),
+            ("while".into(), Token::While),
+            ("for".into(), Token::For),
+            ("break".into(), Token::Break),
+            ("continue".into(), Token::Continue),
+            ("NULL".into(), Token::NULL),
+            ("struct".into(), Token::Struct),
+        ]);
+        let mut lex = Lexer {
+            len: input.len(),
+            input,
+            position: 0,
+            current_token: Token::EOF,
+            current_span: Span::default(),
+            restricted_words,
+            tracked: 0,
+            byte: 0,
+            line: 1,
+            col: 1,
+        };
+        lex.get_next_token()?;
+        Ok(lex)
+    }
+}
+
+pub(crate) fn unrecognized(c: char) -> Error {
+    Error::new(
+        ErrorCode::UnrecognizedToken,
+        Span::default(),
+        format!("Unrecognized token: {}", c),
+    )
+}
+
+pub(crate) fn invalid_char() -> Error {
+    Error::new(
+        ErrorCode::InvalidChar,
+        Span::default(),
+        "A char literal holds exactly one char",
+    )
+}
+
+#[cfg(test)]
+mod lexer_tests {
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+
+    #[test]
+    fn lexer_if() {
+        let tok = Lexer::new("if").unwrap();
+        assert_eq!(Token::If, tok.current_token);
+    }
+
+    #[test]
+    fn lexer_else() {
+        let tok = Lexer::new("else").unwrap();
+        assert_eq!(Token::Else, tok.current_token);
+    }
+
+    #[test]
+    fn lexer_test_float() {
+        let mut tok = Lexer::new("1.2+2.3").unwrap();
+        assert_eq!(Token::FLOAT(1.2), tok.current_token);
+        tok.get_next_token().unwrap();
+        assert_eq!(Token::ADDOP(AddOp::PLUS), tok.current_token);
+        tok.get_next_token().unwrap();
+        assert_eq!(Token::FLOAT(2.3), tok.current_token);
+    }
+
+    #[test]
+    fn lexer_peek() {
+        let lex = Lexer::new("1+2").unwrap();
+        assert_eq!(lex.current_token, Token::DIGIT(1));
+        assert_eq!(lex.peek(), Some('+'))
+    }
+
+    #[test]
+    fn lexer_test() {
+        let mut tok = Lexer::new("1+2").unwrap();
+        assert_eq!(Token::DIGIT(1), tok.current_token);
+        tok.get_next_token().unwrap();
+        assert_eq!(Token::ADDOP(AddOp::PLUS), tok.current_token);
+        tok.get_next_token().unwrap();
+        assert_eq!(Token::DIGIT(2), tok.current_token);
+    }
+
+    #[test]
+    fn lexer_return() {
+        let mut tok = Lexer::new("return a").unwrap();
+        assert_eq!(Token::RET, tok.current_token);
+        tok.get_next_token().unwrap();
+        assert_eq!(Token::IDENT("a".into()), tok.current_token);
+    }
+    #[test]
+    fn lexer_test_assign() {
+        let mut tok = Lexer::new("1=2").unwrap();
+        assert_eq!(Token::DIGIT(1), tok.current_token);
+        tok.get_next_token().unwrap();
+        assert_eq!(Token::ASSIGN, tok.current_token);
+        tok.get_next_token().unwrap();
+        assert_eq!(Token::DIGIT(2), tok.current_token);
+    }
+    #[test]
+    fn lexer_test_ident_vs_key() {
+        let mut tok = Lexer::new("int a").unwrap();
+        assert_eq!(Token::Type(Type::INT), tok.current_token);
+        tok.get_next_token().unwrap();
+        assert_eq!(Token::IDENT("a".into()), tok.current_token);
+    }
+    #[test]
+    fn lexer_test_fn() {
+        let tok = Lexer::new("fn").unwrap();
+        assert_eq!(Token::Type(Type::FUNC), tok.current_token);
+    }
+
+    #[test]
+    fn lexer_bool() {
+        let mut tok = Lexer::new("bool true false").unwrap();
+        assert_eq!(Token::Type(Type::BOOL), tok.current_token);
+        tok.get_next_token().unwrap();
+        assert_eq!(Token::BOOL(Bool::True), tok.current_token);
+        tok.get_next_token().unwrap();
+        assert_eq!(Token::BOOL(Bool::False), tok.current_token);
+    }
+
+    #[test]
+    fn lexer_float() {
+        let mut tok = Lexer::new("float f = 0.125;").unwrap();
+        assert_eq!(Token::Type(Type::FLOAT), tok.current_token);
+        tok.get_next_token().unwrap();
+        tok.get_next_token().unwrap();
+        tok.get_next_token().unwrap();
+        assert_eq!(Token::FLOAT(0.125), tok.current_token);
+    }
+
+    #[test]
+    fn lexer_compare() {
+        let mut tok = Lexer::new("< <= > >= == !=").unwrap();
+        for op in &[
+            Compare::LT,
+            Compare::LE,
+            Compare::GT,
+            Compare::GE,
+            Compare::EQ,
+            Compare::NE,
+        ] {
+            assert_eq!(Token::COMPARE(*op), tok.current_token);
+            tok.get_next_token().unwrap();
+        }
+    }
+
+    #[test]
+    fn lexer_logic() {
+        let mut tok = Lexer::new("a && !b || c != d").unwrap();
+        tok.get_next_token().unwrap();
+        assert_eq!(Token::LOGIC(Logic::AND), tok.current_token);
+        tok.get_next_token().unwrap();
+        assert_eq!(Token::UNOP(UnaryOp::NOT), tok.current_token);
+        tok.get_next_token().unwrap();
+        tok.get_next_token().unwrap();
+        assert_eq!(Token::LOGIC(Logic::OR), tok.current_token);
+        tok.get_next_token().unwrap();
+        tok.get_next_token().unwrap();
+        assert_eq!(Token::COMPARE(Compare::NE), tok.current_token);
+
+        let mut tok = Lexer::new("a | b").unwrap();
+        assert_eq!(
+            ErrorCode::UnrecognizedToken,
+            tok.get_next_token().unwrap_err().code()
+        );
+    }
+
+    #[test]
+    fn lexer_compound_assign() {
+        let mut tok = Lexer::new("a += b -= c *= d /= e %= f++ - --g -> h").unwrap();
+        let expected = vec![
+            Token::ADDASSIGN(AddOp::PLUS),
+            Token::ADDASSIGN(AddOp::MINUS),
+            Token::MULASSIGN(MulOp::MULT),
+            Token::MULASSIGN(MulOp::DIV),
+            Token::MULASSIGN(MulOp::MODU),
+        ];
+        for op in expected {
+            tok.get_next_token().unwrap();
+            assert_eq!(op, tok.current_token);
+            tok.get_next_token().unwrap();
+        }
+        tok.get_next_token().unwrap();
+        assert_eq!(Token::INCR, tok.current_token);
+        tok.get_next_token().unwrap();
+        assert_eq!(Token::ADDOP(AddOp::MINUS), tok.current_token);
+        tok.get_next_token().unwrap();
+        assert_eq!(Token::DECR, tok.current_token);
+        tok.get_next_token().unwrap();
+        tok.get_next_token().unwrap();
+        assert_eq!(Token::ARROW, tok.current_token);
+    }
+
+    #[test]
+    fn lexer_loop_keywords() {
+        let mut tok = Lexer::new("while for break continue").unwrap();
+        assert_eq!(Token::While, tok.current_token);
+        tok.get_next_token().unwrap();
+        assert_eq!(Token::For, tok.current_token);
+        tok.get_next_token().unwrap();
+        assert_eq!(Token::Break, tok.current_token);
+        tok.get_next_token().unwrap();
+        assert_eq!(Token::Continue, tok.current_token);
+    }
+
+    #[test]
+    fn lexer_unrecognized_token() {
+        let mut tok = Lexer::new("1 $ 2").unwrap();
+        assert_eq!(
+            Err(Error::new(
+                ErrorCode::UnrecognizedToken,
+                Span { start: 2, end: 3, line: 1, col: 3 },
+                "Unrecognized token: $"
+            )),
+            tok.get_next_token()
+        );
+    }
+
+    #[test]
+    fn lexer_errors_instead_of_panicking() {
+        assert_eq!(ErrorCode::UnrecognizedToken, Lexer::new("@").err().unwrap().code());
+        assert_eq!(ErrorCode::InvalidNumber, Lexer::new("99999999999").err().unwrap().code());
+        assert_eq!(ErrorCode::EmptyInput, Lexer::new("").err().unwrap().code());
+    }
+
+    #[test]
+    fn lexer_spans() {
+        let mut tok = Lexer::new("int a;\n  // ñ comment\n  ab = 3").unwrap();
+        assert_eq!(Span { start: 0, end: 3, line: 1, col: 1 }, tok.current_span);
+        tok.get_next_token().unwrap();
+        assert_eq!(Span { start: 4, end: 5, line: 1, col: 5 }, tok.current_span);
+        tok.get_next_token().unwrap();
+        tok.get_next_token().unwrap();
+        assert_eq!(Token::IDENT("ab".into()), tok.current_token);
+        assert_eq!(Span { start: 25, end: 27, line: 3, col: 3 }, tok.current_span);
+    }
+
+    #[test]
+    fn lexer_string() {
+        let mut tok = Lexer::new(r#"string s = "a\tb\"c\n";"#).unwrap();
+        assert_eq!(Token::Type(Type::STRING), tok.current_token);
+        tok.get_next_token().unwrap();
+        tok.get_next_token().unwrap();
+        tok.get_next_token().unwrap();
+        assert_eq!(Token::STRING("a\tb\"c\n".into()), tok.current_token);
+        assert_eq!(Span { start: 11, end: 22, line: 1, col: 12 }, tok.current_span);
+        tok.get_next_token().unwrap();
+        assert_eq!(Token::SEMI, tok.current_token);
+    }
+
+    #[test]
+    fn lexer_underscore_ident() {
+        let mut tok = Lexer::new("_a to_string").unwrap();
+        assert_eq!(Token::IDENT("_a".into()), tok.current_token);
+        tok.get_next_token().unwrap();
+        assert_eq!(Token::IDENT("to_string".into()), tok.current_token);
+    }
+
+    #[test]
+    fn lexer_string_errors() {
+        let err = Lexer::new("\"abc\n\"").err().unwrap();
+        assert_eq!(ErrorCode::UnterminatedString, err.code());
+        let err = Lexer::new(r#""a\qb""#).err().unwrap();
+        assert_eq!(ErrorCode::InvalidEscape, err.code());
+        assert_eq!("Unknown escape sequence: \\q", err.message());
+    }
+
+    #[test]
+    fn lexer_char() {
+        let mut tok = Lexer::new(r"char c = 'a' + '\n' + '\''").unwrap();
+        assert_eq!(Token::Type(Type::CHAR), tok.current_token);
+        for _ in 0..3 {
+            tok.get_next_token().unwrap();
+        }
+        assert_eq!(Token::CHAR('a'), tok.current_token);
+        assert_eq!(Span { start: 9, end: 12, line: 1, col: 10 }, tok.current_span);
+        tok.get_next_token().unwrap();
+        tok.get_next_token().unwrap();
+        assert_eq!(Token::CHAR('\n'), tok.current_token);
+        tok.get_next_token().unwrap();
+        tok.get_next_token().unwrap();
+        assert_eq!(Token::CHAR('\''), tok.current_token);
+    }
+
+    #[test]
+    fn lexer_char_errors() {
+        let cases = [
+            ("''", ErrorCode::InvalidChar),
+            ("'ab'", ErrorCode::InvalidChar),
+            ("'a", ErrorCode::InvalidChar),
+            (r"'\q'", ErrorCode::InvalidEscape),
+        ];
+        for (source, code) in cases.iter() {
+            assert_eq!(*code, Lexer::new(source).err().unwrap().code(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn lexer_struct() {
+        let mut tok = Lexer::new("struct Point p; p.x").unwrap();
+        let expected = [
+            Token::Struct,
+            Token::IDENT("Point".into()),
+            Token::IDENT("p".into()),
+            Token::SEMI,
+            Token::IDENT("p".into()),
+            Token::DOT,
+            Token::IDENT("x".into()),
+        ];
+        for token in expected.iter() {
+            assert_eq!(*token, tok.current_token);
+            tok.get_next_token().unwrap();
+        }
+        assert_eq!(Token::EOF, tok.current_token);
+    }
+
+    #[test]
+    fn lexer_pointer() {
+        let mut tok = Lexer::new("int *p = &x; p = NULL && y").unwrap();
+        let expected = [
+            Token::Type(Type::INT),
+            Token::MULOP(MulOp::MULT),
+            Token::IDENT("p".into()),
+            Token::ASSIGN,
+            Token::AMP,
+            Token::IDENT("x".into()),
+            Token::SEMI,
+            Token::IDENT("p".into()),
+            Token::ASSIGN,
+            Token::NULL,
+            Token::LOGIC(Logic::AND),
+        ];
+        for token in expected.iter() {
+            assert_eq!(*token, tok.current_token);
+            tok.get_next_token().unwrap();
+        }
+    }
+}
@@ -0,0 +1,39 @@
+#![deny(missing_docs)]
+#![allow(clippy::upper_case_acronyms)]
+//! This crate is made as a test of skills of some sort.
+//! It Takes code inputs and returns numeric outputs for the most part.
+//!
+//! The interpreter can be embedded: evaluate a script, then read its globals
+//! or call its functions from Rust.
+//!
+//! ```
+//! use rust_version::{Interpreter, Value};
+//!
+//! let mut interpreter = Interpreter::new();
+//! interpreter
+//!     .eval("{ int scale = 3; fn times(int a)->int{ a * scale } }")
+//!     .unwrap();
+//! interpreter.set_global("scale", Value::Int(4)).unwrap();
+//! assert_eq!(Ok(Value::Int(20)), interpreter.call("times", &[Value::Int(5)]));
+//! assert_eq!(Some(Value::Int(4)), interpreter.get_global("scale"));
+//! ```
+
+mod ast;
+mod bytecode;
+mod checker;
+mod error;
+mod interpreter;
+mod lexer;
+mod memory;
+mod parser;
+mod stdlib;
+mod translator;
+mod vm;
+
+pub use ast::{FnDecl, Program, StructDecl};
+pub use error::{Category, Error, ErrorCode, Span};
+pub use interpreter::{
+    Closure, Engine, Interpreter, NativeFn, OverflowPolicy, Value, DEFAULT_MAX_CALL_DEPTH,
+};
+pub use memory::{Allocation, Leak, Pointer};
+pub use lexer::Type;
@@ -0,0 +1,280 @@
+//! Where a program's data lives. Every variable and every array is a block
+//! of cells; blocks declared inside a function or a nested scope are on the
+//! stack and are released when that scope closes, unless a closure captured
+//! them; globals live as long as the interpreter. Heap blocks come from
+//! `malloc` and `calloc` and live until freed. Pointers keep their block
+//! alive, so dereferencing one into a released block is caught instead of
+//! reading reused memory.
+use crate::error::{Error, ErrorCode, Span};
+use crate::interpreter::Value;
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::rc::Rc;
+
+/// How many cells the live heap blocks may hold between them, so a script
+/// that keeps allocating runs out of heap instead of taking down the host.
+pub(crate) const MAX_HEAP_CELLS: usize = 1 << 22;
+
+/// Which part of memory a block is in, which decides when it is released.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Region {
+    Global,
+    Stack,
+    Heap,
+}
+
+/// The cells of one variable, array or heap allocation.
+#[derive(Debug)]
+pub struct Allocation {
+    pub(crate) id: usize,
+    pub(crate) region: Region,
+    // Set by calloc: unwritten cells read as zero instead of uninitialized.
+    pub(crate) zeroed: bool,
+    live: Cell<bool>,
+    // Set when a closure captures the block: it outlives its scope.
+    escaped: Cell<bool>,
+    cells: RefCell<Vec<Option<Value>>>,
+}
+
+// An allocation is only equal to itself, as two arrays in C are.
+impl PartialEq for Allocation {
+    fn eq(&self, other: &Allocation) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Allocation {
+    pub(crate) fn len(&self) -> usize {
+        self.cells.borrow().len()
+    }
+
+    pub(crate) fn is_live(&self) -> bool {
+        self.live.get()
+    }
+
+    /// The value in cell `i`, or `None` if it was never written.
+    pub(crate) fn read(&self, i: usize) -> Option<Value> {
+        self.cells.borrow()[i].clone()
+    }
+
+    pub(crate) fn write(&self, i: usize, value: Value) {
+        self.cells.borrow_mut()[i] = Some(value);
+    }
+
+    /// Copies of the cells, for printing.
+    pub(crate) fn values(&self) -> Vec<Option<Value>> {
+        self.cells.borrow().clone()
+    }
+
+    /**
+     * Ends the block's lifetime. Its values are dropped right away, which
+     * also breaks any cycle of pointers running through it.
+     */
+    pub(crate) fn release(&self) {
+        self.live.set(false);
+        self.cells.borrow_mut().clear();
+    }
+}
+
+/// The address of a cell: a block and an offset into it, which pointer
+/// arithmetic may move past either end.
+#[derive(Clone)]
+pub struct Pointer {
+    pub(crate) block: Rc<Allocation>,
+    pub(crate) offset: isize,
+}
+
+impl Pointer {
+    pub(crate) fn new(block: Rc<Allocation>, offset: isize) -> Pointer {
+        Pointer { block, offset }
+    }
+
+    /// The same block, `n` cells further on.
+    pub(crate) fn add(&self, n: isize) -> Pointer {
+        Pointer::new(Rc::clone(&self.block), self.offset + n)
+    }
+}
+
+impl PartialEq for Pointer {
+    fn eq(&self, other: &Pointer) -> bool {
+        Rc::ptr_eq(&self.block, &other.block) && self.offset == other.offset
+    }
+}
+
+// Only the block's id: its cells may hold pointers back to it.
+impl fmt::Debug for Pointer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Pointer")
+            .field("block", &self.block.id)
+            .field("offset", &self.offset)
+            .finish()
+    }
+}
+
+/// A heap allocation that was never freed, and the call that made it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Leak {
+    /// Where `malloc` or `calloc` was called.
+    pub span: Span,
+    /// How many cells it holds.
+    pub cells: usize,
+}
+
+/// Hands out blocks, releases stack blocks as scopes close and keeps track
+/// of the heap.
+#[derive(Debug, Default)]
+pub(crate) struct Memory {
+    next_id: usize,
+    stack: Vec<Rc<Allocation>>,
+    // How tall the stack was when each open scope was entered.
+    marks: Vec<usize>,
+    // Live heap blocks, oldest first, and where each was allocated.
+    heap: Vec<(Rc<Allocation>, Span)>,
+    // The cells those blocks hold, at most MAX_HEAP_CELLS.
+    heap_cells: usize,
+}
+
+impl Memory {
+    /**
+     * A new block holding `cells`. A stack block is released when the scope
+     * it was allocated in is left, a heap block when it is freed.
+     */
+    pub(crate) fn allocate(&mut self, cells: Vec<Option<Value>>, region: Region) -> Rc<Allocation> {
+        self.next_id += 1;
+        let block = Rc::new(Allocation {
+            id: self.next_id,
+            region,
+            zeroed: false,
+            live: Cell::new(true),
+            escaped: Cell::new(false),
+            cells: RefCell::new(cells),
+        });
+        if region == Region::Stack {
+            self.stack.push(Rc::clone(&block));
+        }
+        block
+    }
+
+    /**
+     * `len` uninitialized cells on the heap, or zeroed ones for calloc. `span`
+     * is the call, reported if the block leaks. Going past `MAX_HEAP_CELLS`
+     * is an error.
+     */
+    pub(crate) fn malloc(
+        &mut self,
+        len: usize,
+        zeroed: bool,
+        span: Span,
+    ) -> Result<Rc<Allocation>, Error> {
+        if len > MAX_HEAP_CELLS - self.heap_cells {
+            return Err(Error::new(
+                ErrorCode::NativeFailure,
+                span,
+                format!(
+                    "out of heap memory: {} cells are in use and {} more would pass the limit of {}",
+                    self.heap_cells, len, MAX_HEAP_CELLS
+                ),
+            ));
+        }
+        self.heap_cells += len;
+        self.next_id += 1;
+        let block = Rc::new(Allocation {
+            id: self.next_id,
+            region: Region::Heap,
+            zeroed,
+            live: Cell::new(true),
+            escaped: Cell::new(false),
+            cells: RefCell::new(vec![None; len]),
+        });
+        self.heap.push((Rc::clone(&block), span));
+        Ok(block)
+    }
+
+    /**
+     * Releases the heap block `pointer` points to the start of. Freeing it
+     * twice, or freeing anything malloc didn't return, is an error.
+     */
+    pub(crate) fn free(&mut self, pointer: &Pointer, span: Span) -> Result<(), Error> {
+        let block = &pointer.block;
+        if block.region != Region::Heap {
+            return Err(Error::new(
+                ErrorCode::InvalidFree,
+                span,
+                "free of memory that wasn't allocated by malloc or calloc",
+            ));
+        }
+        if !block.is_live() {
+            return Err(Error::new(
+                ErrorCode::DoubleFree,
+                span,
+                "free of memory that was already freed",
+            ));
+        }
+        if pointer.offset != 0 {
+            return Err(Error::new(
+                ErrorCode::InvalidFree,
+                span,
+                format!(
+                    "free of a pointer {} cells into its allocation",
+                    pointer.offset
+                ),
+            ));
+        }
+        self.heap_cells -= block.len();
+        block.release();
+        self.heap.retain(|(live, _)| !Rc::ptr_eq(live, block));
+        Ok(())
+    }
+
+    /// The heap blocks that haven't been freed, oldest first.
+    pub(crate) fn leaks(&self) -> Vec<Leak> {
+        self.heap
+            .iter()
+            .map(|(block, span)| Leak {
+                span: *span,
+                cells: block.len(),
+            })
+            .collect()
+    }
+
+    pub(crate) fn enter(&mut self) {
+        self.marks.push(self.stack.len());
+    }
+
+    /// Releases every stack block allocated since the matching `enter`,
+    /// except those captured by a closure.
+    pub(crate) fn leave(&mut self) {
+        let mark = self.marks.pop().unwrap_or(0);
+        for block in self.stack.drain(mark..) {
+            if !block.escaped.get() {
+                block.release();
+            }
+        }
+    }
+
+    /**
+     * Keeps a stack block alive past the end of its scope, for a closure that
+     * captured it: it then lives as long as anything refers to it. The
+     * elements of an array it holds escape with it.
+     */
+    pub(crate) fn escape(&self, block: &Allocation) {
+        if block.region != Region::Stack || block.escaped.replace(true) {
+            return;
+        }
+        for value in block.values().iter().flatten() {
+            self.escape_value(value);
+        }
+    }
+
+    fn escape_value(&self, value: &Value) {
+        match value {
+            Value::Array(_, block) => self.escape(block),
+            Value::Struct(_, fields) => {
+                for field in fields {
+                    self.escape_value(field);
+                }
+            }
+            _ => {}
+        }
+    }
+}
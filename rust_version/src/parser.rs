@@ -0,0 +1,1575 @@
+use crate::ast::*;
+use crate::error::{Error, ErrorCode, Span};
+use crate::lexer::*;
+use std::rc::Rc;
+
+/*
+ *
+ * NOTE: IF IN CAPITALS, CONSUME AND ADVANCE
+ *
+ * Current Grammar:
+ *
+ * program          : MAIN block
+ * block            : LBRACE [statement_list] RBRACE
+ * statement_list   : [(statement [SEMI]|block|function) [statement_list]]
+ * statement        : (expr | declaration | if | while | for | BREAK | CONTINUE)
+ * expr             : atom *(binop atom) //grouped by ORDER OF OPERATIONS
+ * binop            : ASSIGN | ADDASSIGN | MULASSIGN | OR | AND | EQ | NE |
+ *                       LT | GT | LE | GE | PLUS | MINUS | MUL | DIV | MOD
+ * if               : IF expr block [ELSE (if | block)]
+ * while            : WHILE expr block
+ * for              : FOR LPAREN [declaration | expr] SEMI [expr] SEMI [expr] RPAREN block
+ * atom             : (PLUS/MINUS/NOT/INCR/DECR) atom |
+ *                       LPAREN type RPAREN atom |
+ *                       INTEGER | TRUE | FALSE |
+ *                       (LPAREN expr RPAREN | identifier) *(INCR | DECR)
+ * declaration      : type IDENTIFIER [ASSIGN expr]
+ * assignment       : identifier ASSIGN expr
+ * type             : INT|BOOL|FLOAT
+ * identifier       : alphabetic *(alphanumeric) //don't know how to write this
+ * LBRACE = '{'
+ * RBRACE = '}'
+ * LPAREN = '('
+ * RPAREN = ')'
+ * ASSIGN = '='
+ * COMMA  = ','
+ * MAIN   = 'main'
+ *
+ *
+ * Proposed Grammar:
+ *
+ * function : TYPE IDENTIFIER argument_list block
+ * argument_list : LPAREN argument  *(COMMA argument)  RPAREN
+ * argument : TYPE IDENT
+ *
+ * ORDER OF OPERATIONS:
+ *
+ * 0: POSTFIX ++, -- (LEFT TO RIGHT ASS.)
+ * 1: UNARY PLUS/MINUS, NOT, PREFIX ++, -- (RIGHT TO LEFT ASS.)
+ * 2: MULT / DIV / MOD (LEFT TO RIGHT ASS.)
+ * 3: ADD/SUB (LEFT TO RIGHT ASS.)
+ * 4: RELATIONAL, <, >, <=, >= (LEFT TO RIGHT ASS.)
+ * 5: EQUALITY, ==, != (LEFT TO RIGHT ASS.)
+ * 6: LOGICAL AND, && (LEFT TO RIGHT ASS.)
+ * 7: LOGICAL OR, || (LEFT TO RIGHT ASS.)
+ * 8: ASSIGNMENT, =, +=, -=, *=. /=, %=, (RIGHT TO LEFT ASS.)
+ *
+ * Binary operators are parsed by precedence climbing, see binding_power.
+ */
+
+/**
+ * Left and right binding power of a binary operator, following the ORDER OF
+ * OPERATIONS. A left-associative operator binds slightly tighter on its right
+ * side, so `a - b - c` groups as `(a - b) - c`; assignment does the opposite.
+ */
+pub(crate) fn binding_power(token: &Token) -> Option<(u8, u8)> {
+    match token {
+        Token::ASSIGN | Token::ADDASSIGN(_) | Token::MULASSIGN(_) => Some((2, 1)),
+        Token::LOGIC(Logic::OR) => Some((3, 4)),
+        Token::LOGIC(Logic::AND) => Some((5, 6)),
+        Token::COMPARE(Compare::EQ) | Token::COMPARE(Compare::NE) => Some((7, 8)),
+        Token::COMPARE(_) => Some((9, 10)),
+        Token::ADDOP(_) => Some((11, 12)),
+        Token::MULOP(_) => Some((13, 14)),
+        _ => None,
+    }
+}
+
+pub(crate) struct Parser {
+    pub(crate) lexer: Lexer,
+    // How many loops enclose the current statement, so stray breaks are caught early.
+    pub(crate) loop_depth: usize,
+    // Set in the body of a void function, which has no value to end with.
+    pub(crate) in_void_fn: bool,
+}
+
+impl Parser {
+    pub(crate) fn new(input: &str) -> Result<Parser, Error> {
+        Ok(Parser {
+            lexer: Lexer::new(input)?,
+            loop_depth: 0,
+            in_void_fn: false,
+        })
+    }
+
+    /// Builds a parse error located at the current token.
+    pub(crate) fn error(&self, message: &str) -> Error {
+        Error::new(ErrorCode::UnexpectedToken, self.lexer.current_span, message)
+    }
+
+    /// args : LPAREN [expr *(COMMA expr)] RPAREN
+    pub(crate) fn func_call(&mut self) -> Result<Vec<ExprNode>, Error> {
+        let mut args: Vec<ExprNode> = Vec::new();
+
+        self.lexer.get_next_token()?; // ASSUMING already an LPAREN
+        while self.lexer.current_token != Token::RPAREN {
+            args.push(self.expr()?);
+            self.list_separator("an argument")?;
+        }
+
+        self.lexer.get_next_token()?;
+        Ok(args)
+    }
+
+    /**
+     * Between the items of a parenthesized list: consumes a COMMA, which must
+     * be followed by another `item`, or stops at the RPAREN.
+     */
+    pub(crate) fn list_separator(&mut self, item: &str) -> Result<(), Error> {
+        match self.lexer.current_token {
+            Token::COMMA => {
+                self.lexer.get_next_token()?;
+                if self.lexer.current_token == Token::RPAREN {
+                    return Err(self.error(&format!("Expected {} after ','", item)));
+                }
+                Ok(())
+            }
+            Token::RPAREN => Ok(()),
+            _ => Err(self.error("Expected ',' or ')'")),
+        }
+    }
+
+    /// A prefix operator applied to the atom that follows it.
+    pub(crate) fn unary(&mut self, op: UnaryOp, span: Span) -> Result<ExprNode, Error> {
+        self.lexer.get_next_token()?;
+        Ok(Node::new(Expr::Unary(op, Box::new(self.atom()?))).with_span(span))
+    }
+
+    pub(crate) fn atom(&mut self) -> Result<ExprNode, Error> {
+        let span = self.lexer.current_span;
+        match self.lexer.current_token.clone() {
+            Token::DIGIT(i) => {
+                self.lexer.get_next_token()?;
+                Ok(Node::new(Expr::Int(i)).with_span(span))
+            }
+            Token::FLOAT(i) => {
+                self.lexer.get_next_token()?;
+                Ok(Node::new(Expr::Float(i)).with_span(span))
+            }
+            Token::BOOL(i) => {
+                self.lexer.get_next_token()?;
+                Ok(Node::new(Expr::Bool(i == Bool::True)).with_span(span))
+            }
+            Token::STRING(s) => {
+                self.lexer.get_next_token()?;
+                self.postfix(Node::new(Expr::Str(s)).with_span(span))
+            }
+            Token::CHAR(c) => {
+                self.lexer.get_next_token()?;
+                Ok(Node::new(Expr::Char(c)).with_span(span))
+            }
+            Token::LPAREN => {
+                self.lexer.get_next_token()?;
+                match self.lexer.current_token.clone() {
+                    // `(fn (...) {...})` is a lambda, not a cast.
+                    Token::Type(Type::FUNC) => {}
+                    Token::Type(target) => return self.cast(target, span),
+                    _ => {}
+                }
+                let result = self.expr();
+                match self.lexer.current_token {
+                    Token::RPAREN => {
+                        self.lexer.get_next_token()?;
+                        self.postfix(result?)
+                    }
+                    _ => Err(self.error("Expected ')'")),
+                }
+            }
+            Token::ADDOP(AddOp::MINUS) => self.unary(UnaryOp::MINUS, span),
+            Token::ADDOP(AddOp::PLUS) => self.unary(UnaryOp::PLUS, span),
+            Token::UNOP(UnaryOp::NOT) => self.unary(UnaryOp::NOT, span),
+            Token::INCR => self.unary(UnaryOp::PREINC, span),
+            Token::MULOP(MulOp::MULT) => self.unary(UnaryOp::DEREF, span),
+            Token::AMP => self.unary(UnaryOp::ADDR, span),
+            Token::NULL => {
+                self.lexer.get_next_token()?;
+                Ok(Node::new(Expr::Null).with_span(span))
+            }
+            Token::DECR => self.unary(UnaryOp::PREDEC, span),
+            Token::IDENT(i) => {
+                self.lexer.get_next_token()?;
+                self.postfix(Node::new(Expr::Var(i)).with_span(span))
+            }
+            Token::Type(Type::FUNC) => {
+                self.lexer.get_next_token()?;
+                let lambda = self.lambda(span)?;
+                self.postfix(lambda)
+            }
+            _ => Err(self.error("Expected digit, bool, string, char, '+' , '-' , '!' or '(' ")),
+        }
+    }
+
+    /**
+     * `(type) atom`, entered with the type as the current token. Casts bind
+     * like the other prefix operators.
+     */
+    pub(crate) fn cast(&mut self, target: Type, span: Span) -> Result<ExprNode, Error> {
+        match target {
+            Type::INT | Type::FLOAT | Type::BOOL | Type::CHAR => self.lexer.get_next_token()?,
+            _ => return Err(self.error("Can only cast to int, float, bool or char")),
+        }
+        if self.lexer.current_token != Token::RPAREN {
+            return Err(self.error("Expected ')'"));
+        }
+        self.lexer.get_next_token()?;
+        Ok(Node::new(Expr::Cast(target, Box::new(self.atom()?))).with_span(span))
+    }
+
+    /**
+     * Postfix `++`/`--`, `(args)`, `[index]`, `.field` and `->field` after an operand. These
+     * bind tighter than any prefix operator, so `-a++` is `-(a++)`.
+     */
+    pub(crate) fn postfix(&mut self, mut node: ExprNode) -> Result<ExprNode, Error> {
+        loop {
+            let span = self.lexer.current_span;
+            let op = match self.lexer.current_token {
+                Token::INCR => UnaryOp::POSTINC,
+                Token::DECR => UnaryOp::POSTDEC,
+                // A call is located at its callee, so errors point at the name.
+                Token::LPAREN => {
+                    let args = self.func_call()?;
+                    let callee_span = node.span;
+                    node = Node::new(Expr::Call(Box::new(node), args)).with_span(callee_span);
+                    continue;
+                }
+                Token::LBRACKET => {
+                    self.lexer.get_next_token()?;
+                    let index = self.expr()?;
+                    if self.lexer.current_token != Token::RBRACKET {
+                        return Err(self.error("Expected ']'"));
+                    }
+                    self.lexer.get_next_token()?;
+                    node = Node::new(Expr::Index(Box::new(node), Box::new(index))).with_span(span);
+                    continue;
+                }
+                Token::DOT => {
+                    self.lexer.get_next_token()?;
+                    let field_span = self.lexer.current_span;
+                    let field = match self.lexer.current_token.clone() {
+                        Token::IDENT(field) => field,
+                        _ => return Err(self.error("Expected field name")),
+                    };
+                    self.lexer.get_next_token()?;
+                    node = Node::new(Expr::Field(Box::new(node), field)).with_span(field_span);
+                    continue;
+                }
+                // `p->field` is `(*p).field`.
+                Token::ARROW => {
+                    self.lexer.get_next_token()?;
+                    let field_span = self.lexer.current_span;
+                    let field = match self.lexer.current_token.clone() {
+                        Token::IDENT(field) => field,
+                        _ => return Err(self.error("Expected field name")),
+                    };
+                    self.lexer.get_next_token()?;
+                    let target =
+                        Node::new(Expr::Unary(UnaryOp::DEREF, Box::new(node))).with_span(span);
+                    node = Node::new(Expr::Field(Box::new(target), field)).with_span(field_span);
+                    continue;
+                }
+                _ => return Ok(node),
+            };
+            self.lexer.get_next_token()?;
+            node = Node::new(Expr::Unary(op, Box::new(node))).with_span(span);
+        }
+    }
+
+    /**
+     * Precedence climbing: parses operators that bind at least as tightly as
+     * `min_power`, folding left-associative chains into the left operand.
+     */
+    pub(crate) fn expr_bp(&mut self, min_power: u8) -> Result<ExprNode, Error> {
+        let left = self.atom()?;
+        self.climb(left, min_power)
+    }
+
+    /// The rest of `expr_bp` once its first operand, `left`, is parsed.
+    pub(crate) fn climb(&mut self, mut left: ExprNode, min_power: u8) -> Result<ExprNode, Error> {
+        while let Some((left_power, right_power)) = binding_power(&self.lexer.current_token) {
+            if left_power < min_power {
+                break;
+            }
+            let op = self.lexer.current_token.clone();
+            let span = self.lexer.current_span;
+            self.lexer.get_next_token()?;
+
+            let right = Box::new(self.expr_bp(right_power)?);
+            let kind = match op {
+                Token::ASSIGN => Expr::Assign(None, Box::new(left), right),
+                Token::ADDASSIGN(_) | Token::MULASSIGN(_) => {
+                    Expr::Assign(BinOp::from_token(&op), Box::new(left), right)
+                }
+                _ => match BinOp::from_token(&op) {
+                    Some(op) => Expr::Binary(op, Box::new(left), right),
+                    None => return Err(self.error("Expected a binary operator")),
+                },
+            };
+            left = Node::new(kind).with_span(span);
+        }
+        Ok(left)
+    }
+
+    pub(crate) fn expr(&mut self) -> Result<ExprNode, Error> {
+        self.expr_bp(0)
+    }
+
+    /// argList : LPAREN [param *(COMMA param)] RPAREN, left at the RPAREN.
+    pub(crate) fn get_arg_list(&mut self) -> Result<Vec<(Type, String)>, Error> {
+        if Token::LPAREN != self.lexer.current_token {
+            return Err(self.error("expected '('"));
+        }
+        self.lexer.get_next_token()?;
+        let mut result: Vec<(Type, String)> = Vec::new();
+        while self.lexer.current_token != Token::RPAREN {
+            let t = self.type_spec()?;
+
+            if let Token::IDENT(i) = self.lexer.current_token.clone() {
+                self.lexer.get_next_token()?;
+                // `type name[]`, or `type name[N]` where, like in C, N is ignored.
+                if self.lexer.current_token == Token::LBRACKET {
+                    self.lexer.get_next_token()?;
+                    if let Token::DIGIT(_) = self.lexer.current_token {
+                        self.lexer.get_next_token()?;
+                    }
+                    if self.lexer.current_token != Token::RBRACKET {
+                        return Err(self.error("Expected ']'"));
+                    }
+                    self.lexer.get_next_token()?;
+                    result.push((Type::ARRAY(Box::new(t)), i));
+                } else {
+                    result.push((t, i));
+                }
+                self.list_separator("a parameter")?;
+            } else {
+                return Err(self.error(&format!(
+                    "Expected Identifier, current token: {:?}",
+                    self.lexer.current_token
+                )));
+            }
+        }
+        // self.lexer.get_next_token()?;
+
+        if Token::RPAREN != self.lexer.current_token {
+            return Err(self.error(&format!(
+                "Expected Identifier, current token: {:?}",
+                self.lexer.current_token
+            )));
+        }
+        Ok(result)
+    }
+
+    pub(crate) fn return_value(&mut self) -> Result<StmtNode, Error> {
+        if Token::RET == self.lexer.current_token {
+            let span = self.lexer.current_span;
+            self.lexer.get_next_token()?;
+            let value = match self.lexer.current_token {
+                Token::SEMI | Token::RBRACE => None,
+                _ => Some(self.expr()?),
+            };
+            Ok(Node::new(Stmt::Return(value)).with_span(span))
+        } else {
+            let expr = self.expr()?;
+            let span = expr.span;
+            Ok(Node::new(Stmt::Expr(expr)).with_span(span))
+        }
+    }
+
+    pub(crate) fn declaration(&mut self) -> Result<StmtNode, Error> {
+        // declaration : type IDENTIFIER [ASSIGN expr] SEMI
+        match self.lexer.current_token.clone() {
+            Token::Type(Type::FUNC) => {
+                let span = self.lexer.current_span;
+                // expect IDENT
+                self.lexer.get_next_token()?;
+                let name_span = self.lexer.current_span;
+                match self.lexer.current_token.clone() {
+                    Token::IDENT(name) => {
+                        self.lexer.get_next_token()?;
+                        if Token::LPAREN != self.lexer.current_token {
+                            // `fn f = g;` declares a variable holding a function.
+                            return self.variable_rest(Type::FUNC, name, name_span);
+                        }
+                        let decl = self.function(name)?;
+                        Ok(Node::new(Stmt::FnDecl(Rc::new(decl))).with_span(name_span))
+                    }
+                    // A statement that starts with a lambda, such as one called at once.
+                    Token::LPAREN => {
+                        let lambda = self.lambda(span)?;
+                        let lambda = self.postfix(lambda)?;
+                        let expr = self.climb(lambda, 0)?;
+                        Ok(Node::new(Stmt::Expr(expr)).with_span(span))
+                    }
+                    _ => Err(self.error("Expected Function Name")),
+                }
+            }
+            Token::Struct => {
+                self.lexer.get_next_token()?;
+                if let Token::IDENT(name) = self.lexer.current_token.clone() {
+                    let span = self.lexer.current_span;
+                    self.lexer.get_next_token()?;
+                    if self.lexer.current_token == Token::LBRACE {
+                        return self.struct_declaration(name, span);
+                    }
+                    let var_type = self.pointer_type(Type::STRUCT(name))?;
+                    self.variable_declaration(var_type)
+                } else {
+                    Err(self.error("Expected struct name"))
+                }
+            }
+            Token::Type(var_type) => {
+                self.lexer.get_next_token()?;
+                let var_type = self.pointer_type(var_type)?;
+                self.variable_declaration(var_type)
+            }
+            _ => Err(self.error("Parsing error: Expected type")),
+        }
+    }
+
+    /// The rest of a declaration after its type: IDENTIFIER [ASSIGN expr], or an array.
+    pub(crate) fn variable_declaration(&mut self, var_type: Type) -> Result<StmtNode, Error> {
+        if let Token::IDENT(name) = self.lexer.current_token.clone() {
+            let span = self.lexer.current_span;
+            self.lexer.get_next_token()?;
+            self.variable_rest(var_type, name, span)
+        } else {
+            Err(self.error("Parsing Error: Expected identifier"))
+        }
+    }
+
+    /// The rest of a declaration after its name: [ASSIGN expr], or an array.
+    pub(crate) fn variable_rest(
+        &mut self,
+        var_type: Type,
+        name: String,
+        span: Span,
+    ) -> Result<StmtNode, Error> {
+        if self.lexer.current_token == Token::LBRACKET {
+            return self.array_declaration(var_type, name, span);
+        }
+
+        let init = if self.lexer.current_token == Token::ASSIGN {
+            self.lexer.get_next_token()?;
+            match &var_type {
+                Type::STRUCT(type_name) if self.lexer.current_token == Token::LBRACE => {
+                    let init_span = self.lexer.current_span;
+                    let items = self.initializer()?;
+                    Some(Node::new(Expr::Struct(type_name.clone(), items)).with_span(init_span))
+                }
+                _ => Some(self.expr()?),
+            }
+        } else {
+            None
+        };
+        Ok(Node::new(Stmt::Decl(var_type, name, init)).with_span(span))
+    }
+
+    /**
+     * The rest of a function after its name, entered at the LPAREN:
+     * argList [ARROW type] block.
+     */
+    pub(crate) fn function(&mut self, name: String) -> Result<FnDecl, Error> {
+        let params = self.get_arg_list()?;
+        let mut ret = Type::NONE;
+
+        self.lexer.get_next_token()?;
+        if Token::ARROW == self.lexer.current_token {
+            self.lexer.get_next_token()?;
+            ret = self.type_spec()?;
+        }
+        if Token::LBRACE != self.lexer.current_token {
+            return Err(self.error("Expected '->' or {"));
+        }
+        // A function body starts outside of any loop.
+        let loop_depth = std::mem::replace(&mut self.loop_depth, 0);
+        let in_void_fn = std::mem::replace(&mut self.in_void_fn, ret == Type::NONE);
+        let body = self.parse_block();
+        self.loop_depth = loop_depth;
+        self.in_void_fn = in_void_fn;
+        Ok(FnDecl {
+            name,
+            ret,
+            params,
+            body: body?,
+        })
+    }
+
+    /// lambda : FN argList [ARROW type] block, entered after the FN.
+    pub(crate) fn lambda(&mut self, span: Span) -> Result<ExprNode, Error> {
+        let decl = self.function(LAMBDA.to_string())?;
+        Ok(Node::new(Expr::Lambda(Rc::new(decl))).with_span(span))
+    }
+
+    /// type : (TYPE | STRUCT IDENTIFIER) *MUL
+    pub(crate) fn type_spec(&mut self) -> Result<Type, Error> {
+        let base = match self.lexer.current_token.clone() {
+            Token::Type(t) => {
+                self.lexer.get_next_token()?;
+                t
+            }
+            Token::Struct => {
+                self.lexer.get_next_token()?;
+                if let Token::IDENT(name) = self.lexer.current_token.clone() {
+                    self.lexer.get_next_token()?;
+                    Type::STRUCT(name)
+                } else {
+                    return Err(self.error("Expected struct name"));
+                }
+            }
+            _ => {
+                return Err(self.error(&format!(
+                    "Expected type, current token: {:?}",
+                    self.lexer.current_token
+                )))
+            }
+        };
+        self.pointer_type(base)
+    }
+
+    /// Wraps `base` in a pointer for every `*` that follows it.
+    pub(crate) fn pointer_type(&mut self, mut base: Type) -> Result<Type, Error> {
+        while self.lexer.current_token == Token::MULOP(MulOp::MULT) {
+            self.lexer.get_next_token()?;
+            base = Type::POINTER(Box::new(base));
+        }
+        Ok(base)
+    }
+
+    /**
+     * The rest of `STRUCT IDENTIFIER LBRACE *(type IDENTIFIER SEMI) RBRACE
+     * [SEMI]`, entered at the LBRACE.
+     */
+    pub(crate) fn struct_declaration(
+        &mut self,
+        name: String,
+        span: Span,
+    ) -> Result<StmtNode, Error> {
+        self.lexer.get_next_token()?;
+        let mut fields: Vec<(Type, String)> = Vec::new();
+        while self.lexer.current_token != Token::RBRACE {
+            let field_type = self.type_spec()?;
+            let field = match self.lexer.current_token.clone() {
+                Token::IDENT(field) => field,
+                _ => return Err(self.error("Expected field name")),
+            };
+            if fields.iter().any(|(_, other)| *other == field) {
+                return Err(Error::new(
+                    ErrorCode::Redeclaration,
+                    self.lexer.current_span,
+                    format!("Field {} is declared twice", field),
+                ));
+            }
+            self.lexer.get_next_token()?;
+            match self.lexer.current_token {
+                Token::SEMI => self.lexer.get_next_token()?,
+                Token::LBRACKET => return Err(self.error("Array fields are not supported")),
+                _ => return Err(self.error("Expected SEMI")),
+            }
+            fields.push((field_type, field));
+        }
+        self.lexer.get_next_token()?;
+        // C wants a SEMI after the closing brace; here it's optional.
+        if self.lexer.current_token == Token::SEMI {
+            self.lexer.get_next_token()?;
+        }
+        let decl = StructDecl { name, fields };
+        Ok(Node::new(Stmt::StructDecl(Rc::new(decl))).with_span(span))
+    }
+
+    /**
+     * The rest of `type IDENTIFIER LBRACKET [DIGIT] RBRACKET [ASSIGN
+     * initializer]`, entered at the LBRACKET. The size can be left out when
+     * the initializer gives it.
+     */
+    pub(crate) fn array_declaration(
+        &mut self,
+        elem: Type,
+        name: String,
+        span: Span,
+    ) -> Result<StmtNode, Error> {
+        self.lexer.get_next_token()?;
+        let size = match self.lexer.current_token {
+            Token::DIGIT(n) if n > 0 => {
+                self.lexer.get_next_token()?;
+                Some(n as usize)
+            }
+            Token::RBRACKET => None,
+            _ => return Err(self.error("Array size must be a positive int literal")),
+        };
+        if self.lexer.current_token != Token::RBRACKET {
+            return Err(self.error("Expected ']'"));
+        }
+        self.lexer.get_next_token()?;
+
+        let init_span = self.lexer.current_span;
+        let items = if self.lexer.current_token == Token::ASSIGN {
+            self.lexer.get_next_token()?;
+            self.initializer()?
+        } else {
+            Vec::new()
+        };
+        let len = match size {
+            Some(len) if items.len() > len => {
+                return Err(Error::new(
+                    ErrorCode::UnexpectedToken,
+                    init_span,
+                    format!("Too many initializers for an array of {}", len),
+                ))
+            }
+            Some(len) => len,
+            None if items.is_empty() => {
+                return Err(self.error("An array without a size needs initializers"))
+            }
+            None => items.len(),
+        };
+        let init = Node::new(Expr::Array(elem.clone(), len, items)).with_span(span);
+        Ok(Node::new(Stmt::Decl(Type::ARRAY(Box::new(elem)), name, Some(init))).with_span(span))
+    }
+
+    /// initializer : LBRACE [expr *(COMMA expr) [COMMA]] RBRACE
+    pub(crate) fn initializer(&mut self) -> Result<Vec<ExprNode>, Error> {
+        if self.lexer.current_token != Token::LBRACE {
+            return Err(self.error("Expected '{'"));
+        }
+        self.lexer.get_next_token()?;
+        let mut items = Vec::new();
+        while self.lexer.current_token != Token::RBRACE {
+            items.push(self.expr()?);
+            match self.lexer.current_token {
+                Token::COMMA => self.lexer.get_next_token()?,
+                Token::RBRACE => {}
+                _ => return Err(self.error("Expected ',' or '}'")),
+            }
+        }
+        self.lexer.get_next_token()?;
+        Ok(items)
+    }
+
+    pub(crate) fn get_if_body(&mut self) -> Result<Block, Error> {
+        if Token::LBRACE != self.lexer.current_token {
+            return Err(self.error("expected '{' after condition expression."));
+        }
+        self.lexer.get_next_token()?;
+        let block = self.statement_list()?;
+
+        if Token::RBRACE != self.lexer.current_token {
+            return Err(self.error("expected '}' after condition expression."));
+        }
+
+        Ok(block)
+    }
+
+    pub(crate) fn if_statement(&mut self) -> Result<StmtNode, Error> {
+        //current token is if
+        //grammar: IF expr LBRACE StatementList RBRACE [else_statement]
+        //            ^
+        //Do i need parens? Don't think so.
+        let span = self.lexer.current_span;
+        self.lexer.get_next_token()?;
+
+        let condition = self.expr()?;
+        //grammar: IF expr LBRACE StatementList RBRACE [else_statement]
+        //                  ^
+
+        let block = self.get_if_body()?;
+
+        self.lexer.get_next_token()?;
+
+        let otherwise = if self.lexer.current_token == Token::Else {
+            self.lexer.get_next_token()?;
+            if self.lexer.current_token == Token::If {
+                Some(vec![self.if_statement()?])
+            } else {
+                let block = self.get_if_body()?;
+                self.lexer.get_next_token()?;
+                Some(block)
+            }
+        } else {
+            None
+        };
+        Ok(Node::new(Stmt::If(condition, block, otherwise)).with_span(span))
+    }
+
+    pub(crate) fn loop_body(&mut self) -> Result<Block, Error> {
+        self.loop_depth += 1;
+        let body = self.parse_block();
+        self.loop_depth -= 1;
+        body
+    }
+
+    pub(crate) fn while_statement(&mut self) -> Result<StmtNode, Error> {
+        //grammar: WHILE expr block
+        let span = self.lexer.current_span;
+        self.lexer.get_next_token()?;
+
+        let condition = self.expr()?;
+        let body = self.loop_body()?;
+
+        Ok(Node::new(Stmt::While(condition, body)).with_span(span))
+    }
+
+    pub(crate) fn for_statement(&mut self) -> Result<StmtNode, Error> {
+        //grammar: FOR LPAREN [declaration | expr] SEMI [expr] SEMI [expr] RPAREN block
+        let span = self.lexer.current_span;
+        self.lexer.get_next_token()?;
+        if Token::LPAREN != self.lexer.current_token {
+            return Err(self.error("expected '(' after for"));
+        }
+        self.lexer.get_next_token()?;
+
+        let init = if Token::SEMI == self.lexer.current_token {
+            None
+        } else if let Token::Type(_) | Token::Struct = self.lexer.current_token {
+            Some(Box::new(self.declaration()?))
+        } else {
+            let expr = self.expr()?;
+            let span = expr.span;
+            Some(Box::new(Node::new(Stmt::Expr(expr)).with_span(span)))
+        };
+        if Token::SEMI != self.lexer.current_token {
+            return Err(self.error("Expected SEMI"));
+        }
+        self.lexer.get_next_token()?;
+
+        let condition = if Token::SEMI == self.lexer.current_token {
+            None
+        } else {
+            Some(self.expr()?)
+        };
+        if Token::SEMI != self.lexer.current_token {
+            return Err(self.error("Expected SEMI"));
+        }
+        self.lexer.get_next_token()?;
+
+        let step = if Token::RPAREN == self.lexer.current_token {
+            None
+        } else {
+            Some(self.expr()?)
+        };
+        if Token::RPAREN != self.lexer.current_token {
+            return Err(self.error("Expected ')'"));
+        }
+        self.lexer.get_next_token()?;
+
+        let body = self.loop_body()?;
+
+        Ok(Node::new(Stmt::For(init, condition, step, body)).with_span(span))
+    }
+
+    pub(crate) fn loop_control(&mut self) -> Result<StmtNode, Error> {
+        let span = self.lexer.current_span;
+        if self.loop_depth == 0 {
+            return Err(Error::new(
+                ErrorCode::MisplacedControlFlow,
+                span,
+                format!("{:?} outside of a loop", self.lexer.current_token),
+            ));
+        }
+        let result = match self.lexer.current_token {
+            Token::Break => Stmt::Break,
+            _ => Stmt::Continue,
+        };
+        self.lexer.get_next_token()?;
+        Ok(Node::new(result).with_span(span))
+    }
+
+    pub(crate) fn statement(&mut self) -> Result<StmtNode, Error> {
+        /*
+        statement  : (expr | declaration | if | while | for | BREAK | CONTINUE)
+        */
+        match self.lexer.current_token.clone() {
+            Token::Type(_) | Token::Struct => self.declaration(),
+            Token::If => self.if_statement(),
+            Token::While => self.while_statement(),
+            Token::For => self.for_statement(),
+            Token::Break | Token::Continue => self.loop_control(),
+            _ => self.return_value(),
+        }
+    }
+
+    pub(crate) fn statement_list(&mut self) -> Result<Block, Error> {
+        let mut statements_vec: Block = Vec::new();
+
+        while self.lexer.current_token != Token::RBRACE {
+            if self.lexer.current_token == Token::LBRACE {
+                let span = self.lexer.current_span;
+                let block = self.parse_block()?;
+                statements_vec.push(Node::new(Stmt::Block(block)).with_span(span));
+            } else {
+                let curr = self.statement()?;
+
+                match curr.kind {
+                    Stmt::FnDecl(_)
+                    | Stmt::StructDecl(_)
+                    | Stmt::If(..)
+                    | Stmt::While(..)
+                    | Stmt::For(..) => statements_vec.push(curr),
+                    // The last expression of a block is its value, except inside a loop,
+                    // where the body runs again instead of returning, or a void function.
+                    Stmt::Expr(expr)
+                        if self.lexer.current_token == Token::RBRACE
+                            && self.loop_depth == 0
+                            && !self.in_void_fn =>
+                    {
+                        let value = Node::new(Stmt::Return(Some(expr)));
+                        statements_vec.push(value.with_span(curr.span))
+                    }
+                    _ if self.lexer.current_token == Token::RBRACE => statements_vec.push(curr),
+                    _ if self.lexer.current_token == Token::SEMI => {
+                        self.lexer.get_next_token()?;
+                        statements_vec.push(curr);
+                    }
+                    _ => return Err(self.error("Expected SEMI")),
+                }
+            }
+        }
+        Ok(statements_vec)
+    }
+
+    pub(crate) fn parse_block(&mut self) -> Result<Block, Error> {
+        if self.lexer.current_token == Token::LBRACE {
+            self.lexer.get_next_token()?;
+
+            if self.lexer.current_token == Token::RBRACE {
+                self.lexer.get_next_token()?;
+                Ok(Vec::new())
+            } else {
+                let result = self.statement_list()?;
+
+                if self.lexer.current_token == Token::RBRACE {
+                    self.lexer.get_next_token()?;
+                    Ok(result)
+                } else {
+                    Err(self.error("Expected '}'"))
+                }
+            }
+        } else {
+            Err(self.error("Expected '{'"))
+        }
+    }
+
+    // pub fn start_block(&mut self)->Result<ASTreeNode,String>{
+    //     // self.lexer.get_next_token()?;
+    //     self.parse_block()
+    // }
+}
+
+impl Program {
+    /// Parses a braced program such as `{ int a = 2; a * 3 }`.
+    pub fn parse(source: &str) -> Result<Program, Error> {
+        let body = Parser::new(source)?.parse_block()?;
+        Ok(Program { body })
+    }
+}
+
+#[cfg(test)]
+mod parser_tests {
+    use super::*;
+    use crate::interpreter::Interpreter;
+
+    fn int(n: i32) -> ExprNode {
+        Node::new(Expr::Int(n))
+    }
+
+    fn ident(name: &str) -> ExprNode {
+        Node::new(Expr::Var(name.into()))
+    }
+
+    fn binary(op: BinOp, left: ExprNode, right: ExprNode) -> ExprNode {
+        Node::new(Expr::Binary(op, Box::new(left), Box::new(right)))
+    }
+
+    fn assign(op: Option<BinOp>, target: ExprNode, value: ExprNode) -> ExprNode {
+        Node::new(Expr::Assign(op, Box::new(target), Box::new(value)))
+    }
+
+    fn unary(op: UnaryOp, operand: ExprNode) -> ExprNode {
+        Node::new(Expr::Unary(op, Box::new(operand)))
+    }
+
+    fn call(callee: ExprNode, args: Vec<ExprNode>) -> ExprNode {
+        Node::new(Expr::Call(Box::new(callee), args))
+    }
+
+    fn stmt(kind: Stmt) -> StmtNode {
+        Node::new(kind)
+    }
+
+    const PLUS: BinOp = BinOp::Add(AddOp::PLUS);
+    const MULT: BinOp = BinOp::Mul(MulOp::MULT);
+
+    #[test]
+    fn parser_atom_test_float() {
+        let mut pars = Parser::new("1.2+2").unwrap();
+        assert_eq!(Ok(Node::new(Expr::Float(1.2))), pars.atom())
+    }
+
+    #[test]
+    fn parser_test() {
+        assert_eq!(
+            binary(PLUS, int(1), int(2)),
+            Parser::new("1+2").unwrap().expr().unwrap()
+        )
+    }
+
+    #[test]
+    fn parser_atom_test() {
+        let mut pars = Parser::new("1+2").unwrap();
+        assert_eq!(Ok(int(1)), pars.atom())
+    }
+
+    #[test]
+    fn parser_empty_block() {
+        let root = Parser::new("{}");
+        assert_eq!(Ok(Vec::new()), root.unwrap().parse_block())
+    }
+
+    #[test]
+    fn parser_block_basic() {
+        let root = Parser::new("{1+2;}");
+        assert_eq!(
+            vec![stmt(Stmt::Expr(binary(PLUS, int(1), int(2))))],
+            root.unwrap().parse_block().unwrap()
+        )
+    }
+    #[test]
+    fn parser_block2() {
+        let root = Parser::new("{1+2;3+2;}");
+        assert_eq!(
+            Ok(vec![
+                stmt(Stmt::Expr(binary(PLUS, int(1), int(2)))),
+                stmt(Stmt::Expr(binary(PLUS, int(3), int(2)))),
+            ]),
+            root.unwrap().parse_block()
+        )
+    }
+
+    #[test]
+    fn parser_assignment() {
+        assert_eq!(
+            assign(None, int(1), int(1)),
+            Parser::new("1=1").unwrap().expr().unwrap()
+        )
+    }
+
+    #[test]
+    fn parser_block_with_assign() {
+        let root = Parser::new("{1+2;3+2; int a = 3;}");
+        assert_eq!(
+            Ok(vec![
+                stmt(Stmt::Expr(binary(PLUS, int(1), int(2)))),
+                stmt(Stmt::Expr(binary(PLUS, int(3), int(2)))),
+                stmt(Stmt::Decl(Type::INT, "a".into(), Some(int(3)))),
+            ]),
+            root.unwrap().parse_block()
+        )
+    }
+    #[test]
+    fn parser_block_nosemi() {
+        let root = Parser::new("{1+2;3+2}");
+        assert_eq!(
+            Ok(vec![
+                stmt(Stmt::Expr(binary(PLUS, int(1), int(2)))),
+                stmt(Stmt::Return(Some(binary(PLUS, int(3), int(2))))),
+            ]),
+            root.unwrap().parse_block()
+        )
+    }
+
+    #[test]
+    fn parser_atom_test3() {
+        let mut pars = Parser::new("1+2").unwrap();
+        pars.lexer.get_next_token().unwrap();
+        pars.lexer.get_next_token().unwrap();
+
+        assert_eq!(Ok(int(2)), pars.atom())
+    }
+
+    #[test]
+    fn parser_basic() {
+        assert_eq!(int(1), Parser::new("1").unwrap().expr().unwrap())
+    }
+    #[test]
+    fn parser_test_mult() {
+        assert_eq!(
+            binary(MULT, int(1), int(2)),
+            Parser::new("1*2").unwrap().expr().unwrap()
+        )
+    }
+
+    #[test]
+    fn parser_statement() {
+        assert_eq!(
+            stmt(Stmt::Expr(Parser::new("1+2").unwrap().expr().unwrap())),
+            Parser::new("1+2;").unwrap().statement().unwrap()
+        )
+    }
+
+    #[test]
+    fn parser_precedence() {
+        assert_eq!(
+            binary(PLUS, int(1), binary(MULT, int(2), int(3))),
+            Parser::new("1+2*3").unwrap().expr().unwrap()
+        )
+    }
+
+    #[test]
+    fn parser_return() {
+        assert_eq!(
+            stmt(Stmt::Return(Some(int(3)))),
+            Parser::new("return 3").unwrap().statement().unwrap()
+        )
+    }
+
+    #[test]
+    fn parser_void_function() {
+        let decl = FnDecl {
+            name: "f".into(),
+            ret: Type::NONE,
+            params: vec![(Type::POINTER(Box::new(Type::NONE)), "p".into())],
+            body: vec![stmt(Stmt::Return(None))],
+        };
+        assert_eq!(
+            stmt(Stmt::FnDecl(Rc::new(decl))),
+            Parser::new("fn f(void *p) -> void { return; }")
+                .unwrap()
+                .statement()
+                .unwrap()
+        )
+    }
+
+    #[test]
+    fn parser_basic_declaration() {
+        assert_eq!(
+            stmt(Stmt::Decl(Type::INT, "a".into(), Some(int(3)))),
+            Parser::new("int a = 3").unwrap().statement().unwrap()
+        )
+    }
+
+    #[test]
+    fn parser_declarations() {
+        assert_eq!(
+            vec![stmt(Stmt::Decl(Type::INT, "a".into(), Some(int(3))))],
+            Parser::new("{int a = 3;}").unwrap().parse_block().unwrap()
+        )
+    }
+
+    #[test]
+    fn parser_double_declaration() {
+        let err = Interpreter::new().eval("{int a; int a;}")
+            .unwrap_err();
+        assert_eq!(ErrorCode::Redeclaration, err.code());
+        assert_eq!(
+            "<input>:1:13: [E0402] Variable already declared!\n{int a; int a;}\n            ^",
+            err.render("{int a; int a;}", "<input>")
+        )
+    }
+
+    #[test]
+    fn parser_error_location() {
+        let source = "{\n    int a = 3\n    a\n}";
+        let err = Parser::new(source).unwrap().parse_block().unwrap_err();
+        assert_eq!(
+            Error::new(
+                ErrorCode::UnexpectedToken,
+                Span { start: 20, end: 21, line: 3, col: 5 },
+                "Expected SEMI"
+            ),
+            err
+        );
+        assert_eq!(
+            "main.c:3:5: [E0200] Expected SEMI\n    a\n    ^",
+            err.render(source, "main.c")
+        )
+    }
+
+    #[test]
+    fn parser_logic_precedence() {
+        assert_eq!(
+            binary(
+                BinOp::Logic(Logic::OR),
+                ident("a"),
+                binary(
+                    BinOp::Logic(Logic::AND),
+                    binary(BinOp::Compare(Compare::LT), ident("b"), ident("c")),
+                    unary(UnaryOp::NOT, ident("d"))
+                )
+            ),
+            Parser::new("a || b < c && !d").unwrap().expr().unwrap()
+        )
+    }
+
+    // (source, precedence level, right associative), independent of binding_power.
+    fn binary_operators() -> Vec<(&'static str, u8, bool)> {
+        vec![
+            ("=", 1, true),
+            ("+=", 1, true),
+            ("-=", 1, true),
+            ("*=", 1, true),
+            ("/=", 1, true),
+            ("%=", 1, true),
+            ("||", 2, false),
+            ("&&", 3, false),
+            ("==", 4, false),
+            ("!=", 4, false),
+            ("<", 5, false),
+            (">", 5, false),
+            ("<=", 5, false),
+            (">=", 5, false),
+            ("+", 6, false),
+            ("-", 6, false),
+            ("*", 7, false),
+            ("/", 7, false),
+            ("%", 7, false),
+        ]
+    }
+
+    // The node `left <op> right` should parse into.
+    fn apply(op: &str, left: ExprNode, right: ExprNode) -> ExprNode {
+        let operator = |op: &str| match op {
+            "||" => BinOp::Logic(Logic::OR),
+            "&&" => BinOp::Logic(Logic::AND),
+            "==" => BinOp::Compare(Compare::EQ),
+            "!=" => BinOp::Compare(Compare::NE),
+            "<" => BinOp::Compare(Compare::LT),
+            ">" => BinOp::Compare(Compare::GT),
+            "<=" => BinOp::Compare(Compare::LE),
+            ">=" => BinOp::Compare(Compare::GE),
+            "+" => BinOp::Add(AddOp::PLUS),
+            "-" => BinOp::Add(AddOp::MINUS),
+            "*" => BinOp::Mul(MulOp::MULT),
+            "/" => BinOp::Mul(MulOp::DIV),
+            "%" => BinOp::Mul(MulOp::MODU),
+            _ => unreachable!("{}", op),
+        };
+        match op {
+            "=" => assign(None, left, right),
+            "+=" | "-=" | "*=" | "/=" | "%=" => {
+                assign(Some(operator(&op[..1])), left, right)
+            }
+            _ => binary(operator(op), left, right),
+        }
+    }
+
+    #[test]
+    fn parser_operator_pairs() {
+        for (first, first_level, _) in binary_operators() {
+            for (second, second_level, right_assoc) in binary_operators() {
+                let source = format!("a {} b {} c", first, second);
+                let groups_left = first_level > second_level
+                    || (first_level == second_level && !right_assoc);
+                let expected = if groups_left {
+                    apply(second, apply(first, ident("a"), ident("b")), ident("c"))
+                } else {
+                    apply(first, ident("a"), apply(second, ident("b"), ident("c")))
+                };
+                assert_eq!(
+                    expected,
+                    Parser::new(&source).unwrap().expr().unwrap(),
+                    "{}",
+                    source
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn parser_increment_decrement() {
+        assert_eq!(
+            unary(UnaryOp::MINUS, unary(UnaryOp::POSTINC, ident("a"))),
+            Parser::new("-a++").unwrap().expr().unwrap()
+        );
+        assert_eq!(
+            binary(
+                MULT,
+                unary(UnaryOp::PREDEC, ident("a")),
+                unary(UnaryOp::POSTDEC, ident("b"))
+            ),
+            Parser::new("--a * b--").unwrap().expr().unwrap()
+        );
+        assert_eq!(
+            binary(PLUS, unary(UnaryOp::POSTINC, ident("a")), ident("b")),
+            Parser::new("a+++b").unwrap().expr().unwrap()
+        );
+    }
+
+    #[test]
+    fn parser_unary_binds_tighter_than_binary() {
+        for (op, _, _) in binary_operators() {
+            for (prefix, unary_op) in &[
+                ("-", UnaryOp::MINUS),
+                ("+", UnaryOp::PLUS),
+                ("!", UnaryOp::NOT),
+            ] {
+                let source = format!("{}a {} b", prefix, op);
+                let expected = apply(op, unary(*unary_op, ident("a")), ident("b"));
+                assert_eq!(
+                    expected,
+                    Parser::new(&source).unwrap().expr().unwrap(),
+                    "{}",
+                    source
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn parser_parentheses_override_precedence() {
+        assert_eq!(
+            binary(MULT, binary(PLUS, ident("a"), ident("b")), ident("c")),
+            Parser::new("(a + b) * c").unwrap().expr().unwrap()
+        )
+    }
+
+    #[test]
+    fn parser_cast() {
+        assert_eq!(
+            binary(
+                PLUS,
+                Node::new(Expr::Cast(Type::INT, Box::new(ident("x")))),
+                int(1)
+            ),
+            Parser::new("(int)x + 1").unwrap().expr().unwrap()
+        )
+    }
+
+    #[test]
+    fn parse_while() {
+        assert_eq!(
+            stmt(Stmt::While(ident("a"), vec![stmt(Stmt::Break)])),
+            Parser::new("while (a) { break; }").unwrap().statement().unwrap()
+        )
+    }
+
+    #[test]
+    fn parse_for_empty_header() {
+        assert_eq!(
+            stmt(Stmt::For(None, None, None, Vec::new())),
+            Parser::new("for (;;) {}").unwrap().statement().unwrap()
+        )
+    }
+
+    #[test]
+    fn parse_break_outside_loop() {
+        let err = Parser::new("{ int a; if (a) { break; } }")
+            .unwrap()
+            .parse_block()
+            .unwrap_err();
+        assert_eq!(ErrorCode::MisplacedControlFlow, err.code());
+        assert_eq!(19, err.span().col);
+
+        let err = Parser::new("{ while (1) { fn f()->int { continue; } } }")
+            .unwrap()
+            .parse_block()
+            .unwrap_err();
+        assert_eq!(ErrorCode::MisplacedControlFlow, err.code());
+    }
+
+    #[test]
+    fn parser_basic_function() {
+        assert_eq!(
+            stmt(Stmt::FnDecl(Rc::new(FnDecl {
+                name: "func".into(),
+                ret: Type::NONE,
+                params: Vec::new(),
+                body: Vec::new(),
+            }))),
+            Parser::new("fn func(){}").unwrap().statement().unwrap()
+        );
+    }
+
+    #[test]
+    fn basic_function_call() {
+        assert_eq! {
+            vec![
+                stmt(Stmt::FnDecl(Rc::new(FnDecl {
+                    name: "returnThree".into(),
+                    ret: Type::INT,
+                    params: Vec::new(),
+                    body: vec![stmt(Stmt::Return(Some(int(3))))],
+                }))),
+                stmt(Stmt::Return(Some(call(ident("returnThree"), Vec::new())))),
+            ],
+            Parser::new("{
+                fn returnThree()->int{
+                    3
+                }
+                returnThree()
+            }").unwrap().parse_block().unwrap()
+        }
+    }
+
+    #[test]
+    fn parse_if() {
+        assert_eq!(
+            vec![
+                stmt(Stmt::Decl(Type::INT, "a".into(), None)),
+                stmt(Stmt::If(
+                    int(1),
+                    vec![stmt(Stmt::Expr(assign(None, ident("a"), int(3))))],
+                    None
+                )),
+                stmt(Stmt::Return(Some(ident("a")))),
+            ],
+            Parser::new(
+                "
+            {
+                int a;
+                if(1){
+                    a = 3;
+                }
+                return a;
+            }
+            "
+            )
+            .unwrap()
+            .parse_block()
+            .unwrap()
+        )
+    }
+
+    #[test]
+    fn parse_else() {
+        let a = vec![
+            stmt(Stmt::Decl(Type::INT, "a".into(), None)),
+            stmt(Stmt::If(
+                int(0),
+                vec![stmt(Stmt::Expr(assign(None, ident("a"), int(3))))],
+                Some(vec![stmt(Stmt::Expr(assign(None, ident("a"), int(5))))]),
+            )),
+            stmt(Stmt::Return(Some(ident("a")))),
+        ];
+        let b = Parser::new(
+            "
+        {
+            int a;
+            if(0){
+                a = 3;
+            }else{
+                a = 5;
+            }
+            return a;
+        }
+        "
+        )
+        .unwrap()
+        .parse_block()
+        .unwrap();
+
+        println!("LEFT: {:#?} \n RIGHT: {:#?}", a,b );
+        assert_eq!(
+            a
+            ,
+            b
+        )
+    }
+
+    #[test]
+    fn parse_else_if() {
+        assert_eq!(
+            stmt(Stmt::If(
+                ident("a"),
+                Vec::new(),
+                Some(vec![stmt(Stmt::If(ident("b"), Vec::new(), Some(Vec::new())))])
+            )),
+            Parser::new("if a {} else if b {} else {}")
+                .unwrap()
+                .statement()
+                .unwrap()
+        )
+    }
+
+    #[test]
+    fn parser_index() {
+        let string = |s: &str| Node::new(Expr::Str(s.into()));
+        let index = |value, index| Node::new(Expr::Index(Box::new(value), Box::new(index)));
+        assert_eq!(
+            binary(PLUS, index(index(ident("a"), int(1)), ident("i")), int(2)),
+            Parser::new("a[1][i] + 2").unwrap().expr().unwrap()
+        );
+        assert_eq!(
+            index(string("abc"), int(0)),
+            Parser::new("\"abc\"[0]").unwrap().expr().unwrap()
+        );
+        assert!(Parser::new("a[1").unwrap().expr().is_err());
+    }
+
+    #[test]
+    fn parser_array_declaration() {
+        let array = |len, items| Node::new(Expr::Array(Type::INT, len, items));
+        let int_array = Type::ARRAY(Box::new(Type::INT));
+        assert_eq!(
+            stmt(Stmt::Decl(int_array.clone(), "a".into(), Some(array(3, vec![])))),
+            Parser::new("int a[3]").unwrap().statement().unwrap()
+        );
+        assert_eq!(
+            stmt(Stmt::Decl(int_array, "a".into(), Some(array(2, vec![int(1), int(2)])))),
+            Parser::new("int a[] = {1, 2,}").unwrap().statement().unwrap()
+        );
+        let invalid = [
+            "int a[]",
+            "int a[0]",
+            "int a[n]",
+            "int a[1] = {1, 2}",
+            "int a[2] = {1 2}",
+        ];
+        for source in invalid.iter() {
+            assert!(Parser::new(source).unwrap().statement().is_err(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn parser_array_param() {
+        let mut parser = Parser::new("fn f(int a[], float b[4]) {}").unwrap();
+        match parser.statement().unwrap().kind {
+            Stmt::FnDecl(decl) => assert_eq!(
+                vec![
+                    (Type::ARRAY(Box::new(Type::INT)), "a".to_string()),
+                    (Type::ARRAY(Box::new(Type::FLOAT)), "b".to_string())
+                ],
+                decl.params
+            ),
+            other => panic!("expected a function, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parser_lambda() {
+        let decl = FnDecl {
+            name: LAMBDA.into(),
+            ret: Type::INT,
+            params: vec![(Type::INT, "x".into())],
+            body: vec![stmt(Stmt::Return(Some(ident("x"))))],
+        };
+        let lambda = Node::new(Expr::Lambda(Rc::new(decl)));
+        assert_eq!(
+            call(lambda.clone(), vec![int(1)]),
+            Parser::new("fn (int x) -> int { x }(1)")
+                .unwrap()
+                .expr()
+                .unwrap()
+        );
+        assert_eq!(
+            stmt(Stmt::Decl(Type::FUNC, "f".into(), Some(lambda))),
+            Parser::new("fn f = fn (int x) -> int { x }")
+                .unwrap()
+                .statement()
+                .unwrap()
+        );
+        assert_eq!(
+            call(call(ident("make"), Vec::new()), vec![int(2)]),
+            Parser::new("make()(2)").unwrap().expr().unwrap()
+        );
+    }
+
+    #[test]
+    fn parser_call_args() {
+        assert_eq!(
+            call(
+                ident("f"),
+                vec![int(1), binary(PLUS, int(2), int(3)), ident("a")]
+            ),
+            Parser::new("f(1, 2 + 3, a)").unwrap().expr().unwrap()
+        );
+        assert_eq!(
+            call(ident("f"), Vec::new()),
+            Parser::new("f()").unwrap().expr().unwrap()
+        );
+        for source in &["f(1 2)", "f(1,)", "f(,1)"] {
+            let err = Parser::new(source).unwrap().expr().unwrap_err();
+            assert_eq!(ErrorCode::UnexpectedToken, err.code(), "{}", source);
+        }
+        for source in &["fn f(int a int b) {}", "fn f(int a,) {}"] {
+            let err = Parser::new(source).unwrap().statement().unwrap_err();
+            assert_eq!(ErrorCode::UnexpectedToken, err.code(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn parser_struct_declaration() {
+        let mut parser = Parser::new("struct Point { int x; float y; struct Point2 z; }").unwrap();
+        match parser.statement().unwrap().kind {
+            Stmt::StructDecl(decl) => assert_eq!(
+                StructDecl {
+                    name: "Point".into(),
+                    fields: vec![
+                        (Type::INT, "x".into()),
+                        (Type::FLOAT, "y".into()),
+                        (Type::STRUCT("Point2".into()), "z".into()),
+                    ],
+                },
+                *decl
+            ),
+            other => panic!("expected a struct declaration, got {:?}", other),
+        }
+        let point = Type::STRUCT("Point".into());
+        assert_eq!(
+            stmt(Stmt::Decl(point.clone(), "p".into(), None)),
+            Parser::new("struct Point p").unwrap().statement().unwrap()
+        );
+        let init = Node::new(Expr::Struct("Point".into(), vec![int(1), int(2)]));
+        assert_eq!(
+            stmt(Stmt::Decl(point, "p".into(), Some(init))),
+            Parser::new("struct Point p = {1, 2}").unwrap().statement().unwrap()
+        );
+        let invalid = [
+            "struct { int x; }",
+            "struct P { int x }",
+            "struct P { int x; int x; }",
+            "struct P { int a[2]; }",
+            "struct P { x; }",
+        ];
+        for source in invalid.iter() {
+            assert!(Parser::new(source).unwrap().statement().is_err(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn parser_field() {
+        let var = |name: &str| Node::new(Expr::Var(name.into()));
+        let field = |value, name: &str| Node::new(Expr::Field(Box::new(value), name.into()));
+        let index = Node::new(Expr::Index(Box::new(var("ps")), Box::new(int(0))));
+        assert_eq!(
+            field(field(index, "a"), "b"),
+            Parser::new("ps[0].a.b").unwrap().expr().unwrap()
+        );
+        let mut parser = Parser::new("fn f(struct P p) -> struct P { return p; }").unwrap();
+        match parser.statement().unwrap().kind {
+            Stmt::FnDecl(decl) => {
+                assert_eq!(vec![(Type::STRUCT("P".into()), "p".to_string())], decl.params);
+                assert_eq!(Type::STRUCT("P".into()), decl.ret);
+            }
+            other => panic!("expected a function declaration, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parser_pointer() {
+        let var = |name: &str| Node::new(Expr::Var(name.into()));
+        let int_ptr = Type::POINTER(Box::new(Type::INT));
+        let address = unary(UnaryOp::ADDR, var("x"));
+        assert_eq!(
+            stmt(Stmt::Decl(int_ptr.clone(), "p".into(), Some(address))),
+            Parser::new("int *p = &x").unwrap().statement().unwrap()
+        );
+        assert_eq!(
+            stmt(Stmt::Decl(Type::POINTER(Box::new(int_ptr)), "q".into(), None)),
+            Parser::new("int **q").unwrap().statement().unwrap()
+        );
+        let deref = unary(UnaryOp::DEREF, var("p"));
+        assert_eq!(
+            Node::new(Expr::Assign(None, Box::new(deref), Box::new(Node::new(Expr::Null)))),
+            Parser::new("*p = NULL").unwrap().expr().unwrap()
+        );
+        let deref = unary(UnaryOp::DEREF, var("p"));
+        assert_eq!(
+            Node::new(Expr::Field(Box::new(deref), "x".into())),
+            Parser::new("p->x").unwrap().expr().unwrap()
+        );
+        let node_ptr = Type::POINTER(Box::new(Type::STRUCT("Node".into())));
+        let mut parser = Parser::new("struct Node { struct Node *next; }").unwrap();
+        match parser.statement().unwrap().kind {
+            Stmt::StructDecl(decl) => assert_eq!(vec![(node_ptr, "next".into())], decl.fields),
+            other => panic!("expected a struct declaration, got {:?}", other),
+        }
+    }
+}
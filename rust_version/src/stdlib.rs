@@ -0,0 +1,222 @@
+//! The prelude: native functions every interpreter starts with unless the
+//! embedder opts out with `Interpreter::without_prelude`.
+use crate::error::{Error, ErrorCode, Span};
+use crate::interpreter::{as_float, Interpreter, Value, MAX_ARRAY_LEN};
+use crate::lexer::Type;
+use std::convert::TryFrom;
+use std::io::{stdout, Write};
+
+/**
+ * Registers the prelude: output, math, string, conversion and heap functions.
+ */
+pub(crate) fn install(interpreter: &mut Interpreter) {
+    interpreter.register_fn("print", &[Type::ANY], Type::NONE, |args| {
+        print!("{}", args[0]);
+        stdout().flush().map_err(|e| e.to_string())?;
+        Ok(Value::Void)
+    });
+    interpreter.register_fn("println", &[Type::ANY], Type::NONE, |args| {
+        writeln!(stdout(), "{}", args[0]).map_err(|e| e.to_string())?;
+        Ok(Value::Void)
+    });
+
+    // `abs` of the lowest int overflows like `-` does, under the same policy.
+    interpreter.register_builtin(
+        "abs",
+        &[Type::ANY],
+        Type::ANY,
+        |interp, args, span| match args[0] {
+            Value::Int(n) => Ok(Value::Int(interp.overflow.fit(i64::from(n).abs(), span)?)),
+            Value::Float(n) => Ok(Value::Float(n.abs())),
+            ref other => Err(Error::new(
+                ErrorCode::NativeFailure,
+                span,
+                format!("abs: {}", not_a_number(other)),
+            )),
+        },
+    );
+    interpreter.register_fn("min", &[Type::ANY, Type::ANY], Type::ANY, |args| {
+        pick(&args[0], &args[1], i32::min, f64::min)
+    });
+    interpreter.register_fn("max", &[Type::ANY, Type::ANY], Type::ANY, |args| {
+        pick(&args[0], &args[1], i32::max, f64::max)
+    });
+    interpreter.register_fn("pow", &[Type::FLOAT, Type::FLOAT], Type::FLOAT, |args| {
+        Ok(Value::Float(float(&args[0]).powf(float(&args[1]))))
+    });
+    interpreter.register_fn("sqrt", &[Type::FLOAT], Type::FLOAT, |args| {
+        match float(&args[0]) {
+            n if n < 0.0 => Err(format!("{:?} has no real square root", n)),
+            n => Ok(Value::Float(n.sqrt())),
+        }
+    });
+    interpreter.register_fn("floor", &[Type::FLOAT], Type::FLOAT, |args| {
+        Ok(Value::Float(float(&args[0]).floor()))
+    });
+
+    interpreter.register_fn("len", &[Type::ANY], Type::INT, |args| {
+        let count = match &args[0] {
+            Value::Str(s) => s.chars().count(),
+            Value::Array(_, block) => block.len(),
+            other => {
+                return Err(format!(
+                    "expected a string or an array, found {:?}",
+                    other.get_type()
+                ))
+            }
+        };
+        i32::try_from(count)
+            .map(Value::Int)
+            .map_err(|_| format!("length {} does not fit in an int", count))
+    });
+    interpreter.register_fn(
+        "substr",
+        &[Type::STRING, Type::INT, Type::INT],
+        Type::STRING,
+        |args| match (&args[1], &args[2]) {
+            (Value::Int(start), Value::Int(count)) if *start >= 0 && *count >= 0 => {
+                let s = string(&args[0]);
+                let (start, count) = (*start as usize, *count as usize);
+                if start + count > s.chars().count() {
+                    return Err(format!(
+                        "{}..{} is out of bounds for length {}",
+                        start,
+                        start + count,
+                        s.chars().count()
+                    ));
+                }
+                Ok(Value::Str(s.chars().skip(start).take(count).collect()))
+            }
+            (start, count) => Err(format!("invalid range {}, {}", start, count)),
+        },
+    );
+
+    // Conversions the cast syntax doesn't offer.
+    interpreter.register_fn("to_string", &[Type::ANY], Type::STRING, |args| {
+        Ok(Value::Str(args[0].to_string()))
+    });
+    interpreter.register_fn("parse_int", &[Type::STRING], Type::INT, |args| {
+        let s = string(&args[0]);
+        s.trim()
+            .parse()
+            .map(Value::Int)
+            .map_err(|_| format!("{:?} is not an int", s))
+    });
+    interpreter.register_fn("parse_float", &[Type::STRING], Type::FLOAT, |args| {
+        let s = string(&args[0]);
+        s.trim()
+            .parse()
+            .map(Value::Float)
+            .map_err(|_| format!("{:?} is not a float", s))
+    });
+    interpreter.register_fn("round", &[Type::FLOAT], Type::INT, |args| {
+        to_int(float(&args[0]).round())
+    });
+    interpreter.register_fn("ceil", &[Type::FLOAT], Type::INT, |args| {
+        to_int(float(&args[0]).ceil())
+    });
+    interpreter.register_fn("trunc", &[Type::FLOAT], Type::INT, |args| {
+        to_int(float(&args[0]).trunc())
+    });
+
+    // Sizes count cells, each of which holds one value of any type.
+    let void_ptr = Type::POINTER(Box::new(Type::NONE));
+    interpreter.register_builtin(
+        "malloc",
+        &[Type::INT],
+        void_ptr.clone(),
+        |interp, args, span| {
+            let len = size("malloc", &args[0], span)?;
+            interp.malloc(limit("malloc", len, span)?, false, span)
+        },
+    );
+    interpreter.register_builtin(
+        "calloc",
+        &[Type::INT, Type::INT],
+        void_ptr.clone(),
+        |interp, args, span| {
+            let len = size("calloc", &args[0], span)?
+                .checked_mul(size("calloc", &args[1], span)?)
+                .ok_or_else(|| {
+                    Error::new(ErrorCode::NativeFailure, span, "calloc: size overflow")
+                })?;
+            interp.malloc(limit("calloc", len, span)?, true, span)
+        },
+    );
+    interpreter.register_builtin("free", &[void_ptr], Type::NONE, |interp, args, span| {
+        interp.free(&args[0], span)?;
+        Ok(Value::Void)
+    });
+}
+
+/// An allocation size, which can't be negative.
+fn size(name: &str, value: &Value, span: Span) -> Result<usize, Error> {
+    match value {
+        Value::Int(n) if *n >= 0 => Ok(*n as usize),
+        _ => Err(Error::new(
+            ErrorCode::NativeFailure,
+            span,
+            format!("{}: {} is not a valid size", name, value),
+        )),
+    }
+}
+
+/// `len`, if a block that size is allowed: no more cells than an array.
+fn limit(name: &str, len: usize, span: Span) -> Result<usize, Error> {
+    if len > MAX_ARRAY_LEN {
+        return Err(Error::new(
+            ErrorCode::NativeFailure,
+            span,
+            format!(
+                "{}: {} cells is more than the limit of {}",
+                name, len, MAX_ARRAY_LEN
+            ),
+        ));
+    }
+    Ok(len)
+}
+
+fn not_a_number(value: &Value) -> String {
+    format!("expected a number, found {:?}", value.get_type())
+}
+
+/// A FLOAT parameter always holds a float once arguments are checked.
+fn float(value: &Value) -> f64 {
+    as_float(value).expect("FLOAT parameters hold floats")
+}
+
+/// A STRING parameter always holds a string once arguments are checked.
+fn string(value: &Value) -> &str {
+    match value {
+        Value::Str(s) => s,
+        _ => unreachable!("STRING parameter holding {:?}", value),
+    }
+}
+
+fn to_int(n: f64) -> Result<Value, String> {
+    if n.is_nan() || n < f64::from(i32::MIN) || n > f64::from(i32::MAX) {
+        Err(format!("{:?} does not fit in an int", n))
+    } else {
+        Ok(Value::Int(n as i32))
+    }
+}
+
+/**
+ * Applies `ints` when both operands are ints and `floats` otherwise, promoting
+ * like the arithmetic operators do.
+ */
+fn pick(
+    lhs: &Value,
+    rhs: &Value,
+    ints: fn(i32, i32) -> i32,
+    floats: fn(f64, f64) -> f64,
+) -> Result<Value, String> {
+    match (lhs, rhs) {
+        (Value::Int(a), Value::Int(b)) => Ok(Value::Int(ints(*a, *b))),
+        _ => match (as_float(lhs), as_float(rhs)) {
+            (Some(a), Some(b)) => Ok(Value::Float(floats(a, b))),
+            (None, _) => Err(not_a_number(lhs)),
+            (_, None) => Err(not_a_number(rhs)),
+        },
+    }
+}
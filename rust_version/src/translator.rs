@@ -0,0 +1,49 @@
+use crate::ast::*;
+use crate::error::{Error, ErrorCode};
+use crate::lexer::{AddOp, MulOp};
+use crate::parser::Parser;
+
+#[allow(dead_code)]
+pub(crate) struct Translator {
+    pub(crate) parser: Parser,
+}
+
+#[allow(dead_code)]
+impl Translator {
+    pub(crate) fn new(input: &str) -> Result<Translator, Error> {
+        Ok(Translator {
+            parser: Parser::new(input)?,
+        })
+    }
+
+    pub(crate) fn rpn_interp(input: &ExprNode) -> Result<String, Error> {
+        match &input.kind {
+            Expr::Int(n) => Ok(n.to_string()),
+            Expr::Binary(op @ BinOp::Add(_), left, right)
+            | Expr::Binary(op @ BinOp::Mul(_), left, right) => {
+                let symbol = match op {
+                    BinOp::Add(AddOp::PLUS) => '+',
+                    BinOp::Add(AddOp::MINUS) => '-',
+                    BinOp::Mul(MulOp::MULT) => '*',
+                    BinOp::Mul(MulOp::DIV) => '/',
+                    _ => '%',
+                };
+                Ok(format!(
+                    "{} {} {}",
+                    Translator::rpn_interp(left)?,
+                    Translator::rpn_interp(right)?,
+                    symbol
+                ))
+            }
+            _ => Err(Error::new(
+                ErrorCode::Unsupported,
+                input.span,
+                format!("ERROR unexpected expression: {:?}", input.kind),
+            )),
+        }
+    }
+
+    pub(crate) fn rpn_translate(&mut self) -> Result<String, Error> {
+        Translator::rpn_interp(&self.parser.expr()?)
+    }
+}
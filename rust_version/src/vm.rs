@@ -0,0 +1,590 @@
+//! Runs the bytecode `bytecode.rs` compiles, on a stack of values and a
+//! stack of call frames, so script recursion doesn't nest Rust calls. The
+//! operations themselves are the tree-walker's, so both engines give the
+//! same results and the same errors.
+use crate::ast::FnDecl;
+use crate::bytecode::{Chunk, Compiler, Op};
+use crate::error::{Error, ErrorCode, Span};
+use crate::interpreter::*;
+use crate::lexer::Type;
+use crate::memory::{Pointer, Region};
+use std::rc::Rc;
+
+/// A function call in progress, or top level code.
+struct Frame {
+    chunk: Rc<Chunk>,
+    pc: usize,
+    slots: Vec<Option<Var>>,
+    // Scopes open in the frame, which are closed when it returns or fails.
+    scopes: usize,
+    // Where the function was called, or `None` for top level code.
+    call: Option<Span>,
+}
+
+impl Frame {
+    fn new(chunk: Rc<Chunk>, call: Option<Span>) -> Frame {
+        Frame {
+            slots: vec![None; chunk.slots],
+            chunk,
+            pc: 0,
+            scopes: 0,
+            call,
+        }
+    }
+
+    fn local(&self, slot: usize, span: Span) -> Result<&Var, Error> {
+        self.slots[slot].as_ref().ok_or_else(|| {
+            Error::new(
+                ErrorCode::Internal,
+                span,
+                "Interpreting Error: Variable Not Declared",
+            )
+        })
+    }
+}
+
+/// A place on the place stack: `Place` with the names it borrows owned.
+enum Target {
+    Local(Rc<str>, Var),
+    Global(Rc<str>),
+    Cell(Type, Pointer),
+    Field(Box<Target>, usize),
+}
+
+impl Target {
+    fn place(&self) -> Place<'_> {
+        match self {
+            Target::Local(name, var) => Place::Slot(name, var.clone()),
+            Target::Global(name) => Place::Var(name),
+            Target::Cell(cell_type, pointer) => Place::Cell(cell_type.clone(), pointer.clone()),
+            Target::Field(base, i) => Place::Field(Box::new(base.place()), *i),
+        }
+    }
+}
+
+// Elements and dereferences only resolve to cells.
+impl From<Place<'_>> for Target {
+    fn from(place: Place) -> Target {
+        match place {
+            Place::Cell(cell_type, pointer) => Target::Cell(cell_type, pointer),
+            _ => unreachable!("elements and dereferences are cells"),
+        }
+    }
+}
+
+impl Interpreter {
+    /// Runs top level code compiled from a program or a statement.
+    pub(crate) fn run_chunk(&mut self, chunk: Chunk) -> Result<Value, Error> {
+        self.execute(Frame::new(Rc::new(chunk), None), Vec::new())
+    }
+
+    /// `call_value` for the host, run on the VM.
+    pub(crate) fn call_compiled(
+        &mut self,
+        callee: &Value,
+        args: Vec<(Value, Span)>,
+        span: Span,
+    ) -> Result<Value, Error> {
+        let spans = args.iter().map(|(_, span)| *span).collect();
+        let mut stack = vec![callee.clone()];
+        stack.extend(args.into_iter().map(|(arg, _)| arg));
+        self.execute(Frame::new(Rc::new(Chunk::call(spans, span)), None), stack)
+    }
+
+    fn execute(&mut self, frame: Frame, mut stack: Vec<Value>) -> Result<Value, Error> {
+        let mut frames = vec![frame];
+        let result = self.dispatch(&mut frames, &mut stack);
+        // A failed run leaves memory and the call depth as it found them.
+        while let Some(frame) = frames.pop() {
+            self.close(&frame);
+        }
+        result
+    }
+
+    // Closes what a frame still has open when it returns or fails.
+    fn close(&mut self, frame: &Frame) {
+        for _ in 0..frame.scopes {
+            self.memory.leave();
+        }
+        if frame.call.is_some() {
+            self.depth -= 1;
+        }
+    }
+
+    /// The bytecode of a function, compiled the first time it is called.
+    fn chunk(&mut self, decl: &Rc<FnDecl>, captured: &[String]) -> Rc<Chunk> {
+        let chunk = self
+            .chunks
+            .entry(Rc::as_ptr(decl))
+            .or_insert_with(|| Rc::new(Compiler::function(decl, captured)));
+        Rc::clone(chunk)
+    }
+
+    /**
+     * Opens the frame of a call to a script function: checks the depth
+     * limit, then binds the arguments, the function's own name and what a
+     * closure captured, as `call_value` does.
+     */
+    fn enter(
+        &mut self,
+        frames: &mut Vec<Frame>,
+        callee: Value,
+        args: Vec<(Value, Span)>,
+        span: Span,
+    ) -> Result<(), Error> {
+        let (decl, captures) = match &callee {
+            Value::Func(decl) => (decl, &[][..]),
+            Value::Closure(closure) => (&closure.decl, &closure.captures[..]),
+            _ => {
+                return Err(Error::new(
+                    ErrorCode::Internal,
+                    span,
+                    format!("{:?} is not callable", callee),
+                ))
+            }
+        };
+        if self.depth >= self.max_call_depth {
+            return Err(Error::new(
+                ErrorCode::StackOverflow,
+                span,
+                format!(
+                    "stack overflow: more than {} nested calls",
+                    self.max_call_depth
+                ),
+            ));
+        }
+        let names: Vec<String> = captures.iter().map(|(name, _)| name.clone()).collect();
+        let chunk = self.chunk(decl, &names);
+        self.depth += 1;
+        self.memory.enter();
+        let mut frame = Frame::new(Rc::clone(&chunk), Some(span));
+        frame.scopes = 1;
+        frames.push(frame);
+        let frame = frames.last_mut().unwrap();
+        if args.len() != decl.params.len() {
+            return Err(Error::new(
+                ErrorCode::ArityMismatch,
+                span,
+                format!(
+                    "{} takes {} arguments, {} given",
+                    decl.name,
+                    decl.params.len(),
+                    args.len()
+                ),
+            ));
+        }
+        for (i, ((param_type, param), (arg, arg_span))) in decl.params.iter().zip(args).enumerate()
+        {
+            let arg = check_arg(param, param_type, arg, arg_span)?;
+            let block = self.memory.allocate(vec![Some(arg)], Region::Stack);
+            frame.slots[i] = Some((param_type.clone(), block));
+        }
+        let own = Value::Func(Rc::clone(decl));
+        let block = self.memory.allocate(vec![Some(own)], Region::Stack);
+        frame.slots[decl.params.len()] = Some((Type::FUNC, block));
+        for (name, slot) in &chunk.captures {
+            if let Some((_, var)) = captures.iter().find(|(captured, _)| captured == name) {
+                frame.slots[*slot] = Some(var.clone());
+            }
+        }
+        Ok(())
+    }
+
+    fn dispatch(
+        &mut self,
+        frames: &mut Vec<Frame>,
+        stack: &mut Vec<Value>,
+    ) -> Result<Value, Error> {
+        let mut places: Vec<Target> = Vec::new();
+        // The running frame's code, swapped on calls and returns.
+        let mut chunk = Rc::clone(&frames.last().unwrap().chunk);
+        loop {
+            let frame = frames.last_mut().unwrap();
+            let pc = frame.pc;
+            frame.pc += 1;
+            let span = chunk.spans[pc];
+            match &chunk.code[pc] {
+                Op::Const(value) => stack.push(value.clone()),
+                Op::Pop => {
+                    stack.pop();
+                }
+                Op::Load(slot) => {
+                    let value = read_var(frame.local(*slot, span)?, span)?;
+                    stack.push(value);
+                }
+                Op::LoadGlobal(name) => {
+                    let value = self.load_var(name, span)?;
+                    stack.push(value);
+                }
+                Op::LoadFn(slot, name) => {
+                    let var = frame.local(*slot, span)?.clone();
+                    stack.push(function(name, Some(var), span)?);
+                }
+                Op::LoadFnGlobal(name) => {
+                    let value = self.find_fn(name, span)?;
+                    stack.push(value);
+                }
+                Op::Callable => {
+                    let value = stack.pop().unwrap();
+                    stack.push(callable(value, span)?);
+                }
+                Op::Call(spans) => {
+                    let args: Vec<(Value, Span)> = stack
+                        .drain(stack.len() - spans.len()..)
+                        .zip(spans.iter().copied())
+                        .collect();
+                    match stack.pop().unwrap() {
+                        Value::Native(native) => {
+                            let value = self.call_native(&native, args, span)?;
+                            stack.push(value);
+                        }
+                        callee => {
+                            self.enter(frames, callee, args, span)?;
+                            chunk = Rc::clone(&frames.last().unwrap().chunk);
+                        }
+                    }
+                }
+                Op::Closure(lambda, captures) => {
+                    let mut captured = Vec::with_capacity(captures.len());
+                    for (name, slot) in captures.iter() {
+                        let var = frame.local(*slot, span)?.clone();
+                        // It lives as long as the closure does.
+                        self.memory.escape(&var.1);
+                        captured.push((name.clone(), var));
+                    }
+                    let decl = Rc::clone(lambda.decl.as_ref().unwrap());
+                    self.chunks
+                        .entry(Rc::as_ptr(&decl))
+                        .or_insert_with(|| Rc::clone(lambda));
+                    stack.push(Value::Closure(Rc::new(Closure {
+                        decl,
+                        captures: captured,
+                    })));
+                }
+                Op::Unary(op, operand_span) => {
+                    let value = stack.pop().unwrap();
+                    stack.push(self.unary_value(*op, value, *operand_span, span)?);
+                }
+                Op::Arithmetic(op, lhs_span, rhs_span) => {
+                    let rhs = stack.pop().unwrap();
+                    let lhs = stack.pop().unwrap();
+                    let value = self.arithmetic(*op, (lhs, *lhs_span), (rhs, *rhs_span), span)?;
+                    stack.push(value);
+                }
+                Op::Compare(op) => {
+                    let rhs = stack.pop().unwrap();
+                    let lhs = stack.pop().unwrap();
+                    stack.push(self.compare(*op, lhs, rhs, span)?);
+                }
+                Op::Truthy => {
+                    let value = stack.pop().unwrap();
+                    stack.push(Value::Bool(self.is_truthy(&value, span)?));
+                }
+                Op::Short(decided, to) => {
+                    if stack.last() == Some(&Value::Bool(*decided)) {
+                        frame.pc = *to;
+                    } else {
+                        stack.pop();
+                    }
+                }
+                Op::Jump(to) => frame.pc = *to,
+                Op::JumpUnless(to) => {
+                    let value = stack.pop().unwrap();
+                    if !self.is_truthy(&value, span)? {
+                        frame.pc = *to;
+                    }
+                }
+                Op::Cast(target, operand_span) => {
+                    let value = stack.pop().unwrap();
+                    stack.push(self.cast_value(target, value, *operand_span, span)?);
+                }
+                Op::Indexable(strings) => self.indexable(stack.last().unwrap(), span, *strings)?,
+                Op::Index(value_span) => {
+                    let index = stack.pop().unwrap();
+                    let value = stack.pop().unwrap();
+                    stack.push(self.index_value(value, *value_span, (index, span))?);
+                }
+                Op::Field(field) => {
+                    let value = stack.pop().unwrap();
+                    stack.push(self.field_value(value, field, span)?);
+                }
+                Op::ArrayItem(elem) => {
+                    let value = stack.pop().unwrap();
+                    stack.push(array_item(elem, value, span)?);
+                }
+                Op::Array(elem, len, count, region) => {
+                    let values = stack.split_off(stack.len() - count);
+                    let cells = self.fill(elem, *len, values, span)?;
+                    let block = self.memory.allocate(cells, *region);
+                    stack.push(Value::Array(elem.clone(), block));
+                }
+                Op::StructCheck(name, count) => {
+                    self.struct_decl(name, *count, span)?;
+                }
+                Op::StructField(name, i) => {
+                    let value = stack.pop().unwrap();
+                    stack.push(struct_field(&self.structs[name], *i, value, span)?);
+                }
+                Op::Struct(name, count) => {
+                    let fields = stack.split_off(stack.len() - count);
+                    let decl = Rc::clone(&self.structs[name]);
+                    stack.push(self.struct_rest(decl, fields, span)?);
+                }
+                Op::Local(slot, name) => {
+                    let var = frame.local(*slot, span)?.clone();
+                    places.push(Target::Local(Rc::clone(name), var));
+                }
+                Op::Global(name) => places.push(Target::Global(Rc::clone(name))),
+                Op::Element(index_span) => {
+                    let index = stack.pop().unwrap();
+                    let value = stack.pop().unwrap();
+                    let place = self.element_at(value, span, (index, *index_span))?;
+                    places.push(place.into());
+                }
+                Op::Deref => {
+                    let value = stack.pop().unwrap();
+                    places.push(self.deref(value, span)?.into());
+                }
+                Op::Member(field, target_span) => {
+                    let base = places.pop().unwrap();
+                    let current = self.load_struct(&base.place(), span)?;
+                    let i = self.field_position(&current, field, *target_span)?;
+                    places.push(Target::Field(Box::new(base), i));
+                }
+                Op::Peek => {
+                    let value = self.load(&places.last().unwrap().place(), span)?;
+                    stack.push(value);
+                }
+                Op::Read => {
+                    let target = places.pop().unwrap();
+                    stack.push(self.load(&target.place(), span)?);
+                }
+                Op::Store(target_span) => {
+                    let target = places.pop().unwrap();
+                    let value = stack.pop().unwrap();
+                    let value = self.assign_value(&target.place(), value, *target_span, span)?;
+                    stack.push(value);
+                }
+                Op::Step(op, target_span) => {
+                    let target = places.pop().unwrap();
+                    stack.push(self.step(*op, &target.place(), *target_span, span)?);
+                }
+                Op::Address => {
+                    let target = places.pop().unwrap();
+                    stack.push(self.address(target.place(), span)?);
+                }
+                Op::CheckType(var_type) => self.check_type(var_type, span)?,
+                Op::CheckGlobal(name) => {
+                    if self.global_vars.contains_key(&**name) {
+                        return Err(Error::new(
+                            ErrorCode::Redeclaration,
+                            span,
+                            "Variable already declared!",
+                        ));
+                    }
+                }
+                Op::Declare(slot, var_type, name, init) => {
+                    let init = if *init { stack.pop() } else { None };
+                    let value = self.initial(var_type, name, init, span)?;
+                    let block = self.memory.allocate(vec![value], Region::Stack);
+                    frame.slots[*slot] = Some((var_type.clone(), block));
+                }
+                Op::DeclareGlobal(name, var_type, init) => {
+                    let init = if *init { stack.pop() } else { None };
+                    let value = self.initial(var_type, name, init, span)?;
+                    self.declare_global(name.to_string(), var_type.clone(), value, span)?;
+                }
+                Op::DeclareStruct(decl) => self.declare_struct(decl, span)?,
+                Op::Enter => {
+                    self.memory.enter();
+                    frame.scopes += 1;
+                }
+                Op::Leave => {
+                    self.memory.leave();
+                    frame.scopes -= 1;
+                }
+                Op::Return => {
+                    let value = stack.pop().unwrap();
+                    let frame = frames.pop().unwrap();
+                    self.close(&frame);
+                    let value = match (&frame.chunk.decl, frame.call) {
+                        (Some(decl), Some(call)) => check_return(decl, value, call)?,
+                        _ => value,
+                    };
+                    match frames.last() {
+                        Some(caller) => chunk = Rc::clone(&caller.chunk),
+                        None => return Ok(value),
+                    }
+                    stack.push(value);
+                }
+                Op::Fail(error) => return Err(error.clone()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod vm_tests {
+    use super::*;
+
+    /**
+     * Runs `source` on a fresh interpreter with each engine and checks they
+     * agree on the result, or on the error down to its span and message, and
+     * on what leaked. Values are compared as printed, so closures and
+     * pointers compare by what they show.
+     */
+    fn same(source: &str) -> Result<String, Error> {
+        let run = |engine| {
+            let mut interpreter = Interpreter::new();
+            interpreter.set_engine(engine);
+            let result = interpreter.eval(source).map(|value| format!("{:?}", value));
+            (result, interpreter.leaks())
+        };
+        let tree = run(Engine::Tree);
+        assert_eq!(tree, run(Engine::Vm), "{}", source);
+        tree.0
+    }
+
+    #[test]
+    fn vm_runs_programs() {
+        let cases = [
+            ("{ 1 + 2 * 3 }", "Int(7)"),
+            ("{ int a = 6; fn factorial(int a)->int{ if (a){ return a * factorial(a-1) }else{ return 1 } } factorial(6) }", "Int(720)"),
+            ("{ int s = 0; for (int i = 0; i < 10; i++) { if (i == 3) { continue; } if (i == 7) { break; } s += i; } s }", "Int(18)"),
+            ("{ int n = 0; while (1) { n++; if (n > 4) { break; } } n }", "Int(5)"),
+            ("{ int s = 0; for (int i = 0; i < 4; i++) { s += i } s }", "Int(6)"),
+            ("{ fn f(int n)->int { while n > 2 { if n > 4 { n = n - 2 } n-- } return n; } f(9) }", "Int(2)"),
+            ("{ int s = 0; for (int i = 0; i < 3; i++) { for (int j = 0; j < 3; j++) { if (j == i) { continue; } s += j; } } s }", "Int(6)"),
+            ("{ int x = 1; { int x = 2; x += 1; } x }", "Int(1)"),
+            ("{ float f = 1; f / 2 }", "Float(0.5)"),
+            ("{ string s = \"ab\"; s = s + 'c'; s[2] }", "Char('c')"),
+            ("{ char c = 'a'; c += 1; c }", "Char('b')"),
+            ("{ bool b = 1 < 2 && !(2 < 1) || 1 / 0; b }", "Bool(true)"),
+            ("{ (int) 2.9 + (int) 'a' }", "Int(99)"),
+            ("{ int a[3] = {1, 2}; a[2] = a[0] + a[1]; to_string(a) }", "Str(\"{1, 2, 3}\")"),
+            ("{ int a[] = {1, 2, 3}; int *p = a + 1; p[1] = 9; *p + a[2] }", "Int(11)"),
+            ("{ int x = 1; int *p = &x; int **q = &p; **q += 2; x }", "Int(3)"),
+            ("{ struct P { int x; int y; } struct P p = {1}; p.y = 4; p.y++; p.x + p.y }", "Int(6)"),
+            ("{ struct P { int x; } struct P v; struct P *p = &v; p->x = 4; (*p).x + v.x }", "Int(8)"),
+            ("{ int *p = malloc(3); p[2] = 7; int n = p[2]; free(p); n }", "Int(7)"),
+            ("{ int *p = malloc(2); int *q = calloc(1, 1); *q }", "Int(0)"),
+            ("{ fn inc(int *p) { *p += 1; } int x = 1; inc(&x); inc(&x); x }", "Int(3)"),
+            ("{ fn mix(int a, float b, int c)->float { a * b + c } mix(2, 2.5, len(\"ab\")) }", "Float(7.0)"),
+            ("{ fn apply(fn f, int x)->int { f(x) } apply(fn (int n)->int { n * n }, 7) }", "Int(49)"),
+            ("{ fn adder(int n)->fn { return fn (int x)->int { x + n }; } adder(10)(5) }", "Int(15)"),
+            ("{ fn counter()->fn { int c = 0; return fn ()->int { c += 1; return c; }; } fn c = counter(); c(); c() }", "Int(2)"),
+            ("{ int x = 1; fn get = fn ()->int { x }; x = 5; get() }", "Int(5)"),
+            ("{ fn f()->fn { int x = 1; return fn (int x)->int { x }; } f()(7) }", "Int(7)"),
+            ("{ fn outer()->fn { int a = 2; return fn ()->fn { return fn ()->int { a * 3 }; }; } outer()()() }", "Int(6)"),
+            ("{ fn fs[2] = { fn ()->int { 1 }, fn ()->int { 2 } }; fs[0]() + fs[1]() }", "Int(3)"),
+            ("{ int x = 1; int *p = &x; to_string(p) }", "Str(\"&20[0]\")"),
+            ("{ fn f() { return; } f() }", "Void"),
+            ("{ fn f = fn () {}; f }", "Closure(Closure { decl: \"<lambda>\", captures: [] })"),
+        ];
+        for (source, expected) in cases.iter() {
+            assert_eq!(Ok(expected.to_string()), same(source), "{}", source);
+        }
+    }
+
+    #[test]
+    fn vm_reports_errors() {
+        let cases = [
+            ("{ 1 / 0 }", ErrorCode::DivisionByZero),
+            ("{ 2147483647 + 1 }", ErrorCode::IntegerOverflow),
+            ("{ int a[2]; a[2] }", ErrorCode::IndexOutOfBounds),
+            ("{ int a[2000000000]; }", ErrorCode::Unsupported),
+            ("{ int x; x + 1 }", ErrorCode::UninitializedVariable),
+            (
+                "{ int *p; { int x = 1; p = &x; } *p }",
+                ErrorCode::DanglingPointer,
+            ),
+            (
+                "{ fn f() -> int* { int x; return &x; } int *p = f(); *p }",
+                ErrorCode::DanglingPointer,
+            ),
+            ("{ int *p = NULL; *p = 1; }", ErrorCode::NullPointer),
+            (
+                "{ int *p = malloc(1); free(p); *p = 1; }",
+                ErrorCode::UseAfterFree,
+            ),
+            (
+                "{ int *p = malloc(1); free(p); free(p); }",
+                ErrorCode::DoubleFree,
+            ),
+            (
+                "{ struct P { int x; } struct P v; &v.x }",
+                ErrorCode::Unsupported,
+            ),
+            ("{ (char) (0 - 1) }", ErrorCode::InvalidCast),
+            (
+                "{ fn f(int n)->int { f(n + 1) } f(0) }",
+                ErrorCode::StackOverflow,
+            ),
+            (
+                "{ fn f()->int { if (0) { return 1; } } f() }",
+                ErrorCode::MissingReturn,
+            ),
+        ];
+        for (source, code) in cases.iter() {
+            match same(source) {
+                Err(err) => assert_eq!(*code, err.code(), "{}", source),
+                Ok(value) => panic!("{} gave {}", source, value),
+            }
+        }
+    }
+
+    #[test]
+    fn vm_recovers_from_errors() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_engine(Engine::Vm);
+        interpreter.set_max_call_depth(50);
+        interpreter
+            .eval(
+                "{ fn down(int n)->int { int a[2] = {n, n}; if (n == 0) { 1 / n } down(n - 1) } }",
+            )
+            .unwrap();
+        let err = interpreter.call("down", &[Value::Int(10)]).unwrap_err();
+        assert_eq!(ErrorCode::DivisionByZero, err.code());
+        // Every frame the error left was closed, so the depth limit still
+        // counts from zero.
+        assert_eq!(
+            ErrorCode::DivisionByZero,
+            interpreter
+                .call("down", &[Value::Int(49)])
+                .unwrap_err()
+                .code()
+        );
+        assert_eq!(
+            ErrorCode::StackOverflow,
+            interpreter
+                .call("down", &[Value::Int(50)])
+                .unwrap_err()
+                .code()
+        );
+    }
+
+    #[test]
+    fn vm_host_calls_and_statements() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_engine(Engine::Vm);
+        interpreter
+            .eval("{ int scale = 3; fn times(int a)->int{ a * scale } fn c = fn (int x)->int { x + scale }; }")
+            .unwrap();
+        interpreter.set_global("scale", Value::Int(4)).unwrap();
+        assert_eq!(
+            Ok(Value::Int(20)),
+            interpreter.call("times", &[Value::Int(5)])
+        );
+        assert_eq!(Ok(Value::Int(5)), interpreter.call("c", &[Value::Int(1)]));
+        assert_eq!(
+            ErrorCode::ArityMismatch,
+            interpreter.call("times", &[]).unwrap_err().code()
+        );
+        assert_eq!(
+            Ok(Value::Void),
+            interpreter.eval_statement("int y = times(2);")
+        );
+        assert_eq!(Ok(Value::Int(9)), interpreter.eval_statement("y + 1"));
+        assert_eq!(Some(Value::Int(8)), interpreter.get_global("y"));
+    }
+}